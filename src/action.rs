@@ -10,7 +10,39 @@ pub enum Action {
     ToggleFreeze(IsFreeze),
     OnDraw,
     OnEdit(EditAction),
-    Exit,
+    Pick(PickAction),
+    Measure(MeasureAction),
+    Exit(ExitAction),
+    /// 截图已钉在屏幕上，只响应钉图窗口的输入
+    Pin,
+}
+
+/// 退出时对截图的处理
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+pub enum ExitAction {
+    /// 保存到文件，并按配置复制到剪贴板
+    Save,
+    /// 只复制到剪贴板，不写入文件
+    Copy,
+    /// 钉在屏幕上，不写入文件
+    Pin,
+    /// 保存后运行钩子，没有指定钩子时运行配置文件中的所有命名钩子
+    Hook,
+}
+
+/// 取色模式下的状态
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+pub enum PickAction {
+    WaitPress,
+    OnDraw,
+    Done,
+}
+
+/// 测量模式下的状态
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+pub enum MeasureAction {
+    WaitPress,
+    OnDraw,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
@@ -33,6 +65,14 @@ pub enum EditAction {
     BottomLeft,  // 左下角
     BottomRight, // 右下角
     Move,
+    /// 使用标注工具绘制
+    Annotate,
+    /// 在文字框中输入
+    Typing,
+    /// 拖动或缩放选中的标注
+    Object,
+    /// 在操作栏中输入另存为的文件名
+    Rename,
 }
 impl EditAction {
     pub fn to_cursor_shape(&self) -> Shape {
@@ -43,6 +83,10 @@ impl EditAction {
             EditAction::TopLeft | EditAction::BottomRight => Shape::NwseResize, // 左上-右下用对角双向箭头
             EditAction::TopRight | EditAction::BottomLeft => Shape::NeswResize, // 右上-左下用对角双向箭头
             EditAction::Move => Shape::Move,                                    // 移动用移动光标
+            EditAction::Annotate => Shape::Crosshair,
+            EditAction::Typing => Shape::Text,
+            EditAction::Object => Shape::Move,
+            EditAction::Rename => Shape::Default,
         }
     }
 }
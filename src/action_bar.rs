@@ -0,0 +1,134 @@
+use cairo::Context;
+
+use crate::context_menu::MenuItem;
+
+const ITEM_WIDTH: f64 = 96.0;
+const ITEM_HEIGHT: f64 = 28.0;
+const ITEM_GAP: f64 = 4.0;
+const FONT_SIZE: f64 = 14.0;
+/// 操作栏与选区之间的距离
+const MARGIN: f64 = 8.0;
+
+/// 操作栏中的按钮，顺序即显示顺序
+const ITEMS: [MenuItem; 6] = [
+    MenuItem::Save,
+    MenuItem::SaveAs,
+    MenuItem::Copy,
+    MenuItem::Pin,
+    MenuItem::Hook,
+    MenuItem::Discard,
+];
+
+/// NOTE: 编辑模式下显示在选区上方的截图操作栏，只有选择的操作需要时才写入文件
+///
+/// 另存为时按钮替换为文件名输入框
+#[derive(Debug, Clone)]
+pub struct ActionBar {
+    /// 操作栏左上角的全局坐标
+    pub x: f64,
+    pub y: f64,
+    pub items: Vec<MenuItem>,
+    /// 另存为时正在输入的文件名
+    pub rename: Option<String>,
+}
+
+impl ActionBar {
+    /// 右对齐放在选区上方，超出 top 时放到选区内部顶部
+    pub fn new(rect: (i32, i32, i32, i32), top: i32, rename: Option<String>) -> Self {
+        let (sx, sy, ex, _) = rect;
+        let items = ITEMS.to_vec();
+        let width = items.len() as f64 * (ITEM_WIDTH + ITEM_GAP) - ITEM_GAP;
+
+        let above = sy as f64 - MARGIN - ITEM_HEIGHT;
+        let y = if above >= top as f64 {
+            above
+        } else {
+            sy as f64 + MARGIN
+        };
+        Self {
+            x: (ex as f64 - width).max(sx as f64),
+            y,
+            items,
+            rename,
+        }
+    }
+
+    fn width(&self) -> f64 {
+        self.items.len() as f64 * (ITEM_WIDTH + ITEM_GAP) - ITEM_GAP
+    }
+
+    /// 返回全局坐标处的按钮，输入文件名时没有按钮
+    pub fn item_at(&self, gx: f64, gy: f64) -> Option<MenuItem> {
+        if self.rename.is_some() || !self.contains(gx, gy) {
+            return None;
+        }
+        let index = ((gx - self.x) / (ITEM_WIDTH + ITEM_GAP)) as usize;
+        self.items.get(index).copied()
+    }
+
+    /// 是否在操作栏范围内
+    pub fn contains(&self, gx: f64, gy: f64) -> bool {
+        gx >= self.x && gx <= self.x + self.width() && gy >= self.y && gy <= self.y + ITEM_HEIGHT
+    }
+
+    /// 在输出的 cairo 上下文中绘制，origin 为输出左上角的全局坐标
+    pub fn draw(&self, cr: &Context, origin: (i32, i32)) {
+        let x = self.x - origin.0 as f64;
+        let y = self.y - origin.1 as f64;
+
+        cr.save().unwrap();
+        cr.select_font_face(
+            "sans-serif",
+            cairo::FontSlant::Normal,
+            cairo::FontWeight::Normal,
+        );
+        cr.set_font_size(FONT_SIZE);
+        let baseline = y + ITEM_HEIGHT / 2.0 + FONT_SIZE / 3.0;
+
+        // 文件名较长时输入框向右延伸
+        let prompt = self
+            .rename
+            .as_ref()
+            .map(|name| format!("Save as: {}", name));
+        let width = match prompt.as_ref() {
+            Some(prompt) => self
+                .width()
+                .max(cr.text_extents(prompt).unwrap().x_advance() + 24.0),
+            None => self.width(),
+        };
+        cr.set_source_rgba(0.15, 0.15, 0.15, 0.95);
+        cr.rectangle(
+            x - ITEM_GAP,
+            y - ITEM_GAP,
+            width + ITEM_GAP * 2.0,
+            ITEM_HEIGHT + ITEM_GAP * 2.0,
+        );
+        cr.fill().unwrap();
+
+        if let Some(prompt) = prompt {
+            cr.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+            cr.move_to(x + 8.0, baseline);
+            cr.show_text(&prompt).unwrap();
+            // 光标
+            let (cursor_x, _) = cr.current_point().unwrap();
+            cr.rectangle(cursor_x + 1.0, y + 6.0, 1.5, ITEM_HEIGHT - 12.0);
+            cr.fill().unwrap();
+            cr.restore().unwrap();
+            return;
+        }
+
+        for (i, item) in self.items.iter().enumerate() {
+            let item_x = x + i as f64 * (ITEM_WIDTH + ITEM_GAP);
+            cr.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+            cr.move_to(item_x + 8.0, baseline);
+            cr.show_text(item.label()).unwrap();
+
+            let shortcut = item.shortcut();
+            let extents = cr.text_extents(shortcut).unwrap();
+            cr.set_source_rgba(1.0, 1.0, 1.0, 0.5);
+            cr.move_to(item_x + ITEM_WIDTH - 8.0 - extents.x_advance(), baseline);
+            cr.show_text(shortcut).unwrap();
+        }
+        cr.restore().unwrap();
+    }
+}
@@ -0,0 +1,784 @@
+use std::f64::consts::PI;
+
+use cairo::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::{Action, EditAction},
+    redact::{self, Redaction},
+    select_rect::SelectRect,
+};
+
+/// 两点之间距离小于该值时不记录新的画笔点
+const PEN_MIN_STEP: f64 = 2.0;
+/// 文字行高与字号的比例
+const LINE_HEIGHT: f64 = 1.25;
+/// 马赛克块大小和模糊半径与线宽的比例
+const REDACT_STRENGTH: f64 = 3.0;
+/// 荧光笔宽度与线宽的比例
+const HIGHLIGHT_STRENGTH: f64 = 4.0;
+
+/// 标注工具
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tool {
+    Rect,
+    Ellipse,
+    Line,
+    Arrow,
+    Pen,
+    Text,
+    /// 马赛克遮挡
+    Pixelate,
+    /// 模糊遮挡
+    Blur,
+    /// 纯色遮挡
+    Redact,
+    /// 自动递增的步骤序号
+    Step,
+    /// 以正片叠底绘制的荧光笔
+    Highlighter,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 11] = [
+        Tool::Rect,
+        Tool::Ellipse,
+        Tool::Line,
+        Tool::Arrow,
+        Tool::Pen,
+        Tool::Text,
+        Tool::Pixelate,
+        Tool::Blur,
+        Tool::Redact,
+        Tool::Step,
+        Tool::Highlighter,
+    ];
+
+    /// 遮挡工具直接修改像素，导出矢量图时也要栅格化到截图中
+    pub fn is_redaction(self) -> bool {
+        matches!(self, Tool::Pixelate | Tool::Blur | Tool::Redact)
+    }
+}
+
+/// 标注的颜色和线宽
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    pub color: (f64, f64, f64),
+    pub width: f64,
+}
+
+/// 文字标注的内容和字体
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextContent {
+    pub text: String,
+    pub family: String,
+    pub size: f64,
+}
+
+/// 一个标注，坐标为全局逻辑坐标
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub tool: Tool,
+    /// 画笔和荧光笔为所有经过的点，文字为左上角，步骤序号为圆心，其他工具为起点和终点
+    pub points: Vec<(f64, f64)>,
+    pub style: Style,
+    /// 只有文字标注有内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<TextContent>,
+    /// 只有步骤序号有编号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<u32>,
+}
+
+impl Annotation {
+    pub fn new(tool: Tool, start: (f64, f64), style: Style) -> Self {
+        Self {
+            tool,
+            points: vec![start, start],
+            style,
+            text: None,
+            step: None,
+        }
+    }
+
+    pub fn new_step(pos: (f64, f64), style: Style, step: u32) -> Self {
+        Self {
+            tool: Tool::Step,
+            points: vec![pos],
+            style,
+            text: None,
+            step: Some(step),
+        }
+    }
+
+    pub fn new_text(pos: (f64, f64), style: Style, family: &str, size: f64) -> Self {
+        Self {
+            tool: Tool::Text,
+            points: vec![pos],
+            style,
+            text: Some(TextContent {
+                text: String::new(),
+                family: family.to_string(),
+                size,
+            }),
+            step: None,
+        }
+    }
+
+    /// 拖动时更新终点，画笔追加新的点
+    pub fn update(&mut self, pos: (f64, f64)) {
+        match self.tool {
+            Tool::Text => {}
+            // 拖动时移动序号的位置
+            Tool::Step => self.points = vec![pos],
+            Tool::Pen | Tool::Highlighter => {
+                let last = self.points.last().copied().unwrap_or(pos);
+                if (pos.0 - last.0).hypot(pos.1 - last.1) >= PEN_MIN_STEP {
+                    self.points.push(pos);
+                }
+            }
+            _ => {
+                if let Some(end) = self.points.last_mut() {
+                    *end = pos;
+                }
+            }
+        }
+    }
+
+    /// 起点与终点重合的标注没有意义
+    pub fn is_empty(&self) -> bool {
+        if let Some(content) = self.text.as_ref() {
+            return content.text.trim().is_empty();
+        }
+        if self.step.is_some() {
+            return false;
+        }
+        let (Some(start), Some(end)) = (self.points.first(), self.points.last()) else {
+            return true;
+        };
+        self.points.len() <= 2 && start == end
+    }
+
+    /// 步骤序号圆的半径，随线宽变化
+    fn step_radius(&self) -> f64 {
+        self.style.width * 2.0 + 6.0
+    }
+
+    /// 全局坐标是否落在步骤序号的圆内
+    fn step_contains(&self, pos: (f64, f64)) -> bool {
+        match (self.step, self.points.first()) {
+            (Some(_), Some(&(x, y))) => (pos.0 - x).hypot(pos.1 - y) <= self.step_radius(),
+            _ => false,
+        }
+    }
+
+    /// 包含整个标注的全局矩形 (sx, sy, ex, ey)
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        if let (Some(_), Some(&(x, y))) = (self.step, self.points.first()) {
+            let r = self.step_radius();
+            return (x - r, y - r, x + r, y + r);
+        }
+        if let (Some(content), Some(&(x, y))) = (self.text.as_ref(), self.points.first()) {
+            let width = measure_context()
+                .map(|cr| {
+                    set_font(&cr, content);
+                    content
+                        .text
+                        .split('\n')
+                        .map(|line| text_advance(&cr, line))
+                        .fold(0.0, f64::max)
+                })
+                .unwrap_or(0.0);
+            let lines = content.text.split('\n').count() as f64;
+            return (x, y, x + width, y + content.size * LINE_HEIGHT * lines);
+        }
+
+        let mut bounds = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+        for &(x, y) in &self.points {
+            bounds = (
+                bounds.0.min(x),
+                bounds.1.min(y),
+                bounds.2.max(x),
+                bounds.3.max(y),
+            );
+        }
+        // 线条有宽度，边界向外扩展半个线宽
+        let pad = match self.tool {
+            Tool::Highlighter => self.style.width * HIGHLIGHT_STRENGTH / 2.0,
+            _ => self.style.width / 2.0,
+        };
+        (
+            bounds.0 - pad,
+            bounds.1 - pad,
+            bounds.2 + pad,
+            bounds.3 + pad,
+        )
+    }
+
+    /// 将 origin 从矩形 from 映射到矩形 to，得到移动或缩放后的标注
+    ///
+    /// 文字按高度缩放字号，步骤序号只移动圆心
+    fn map_rect(&mut self, origin: &Annotation, from: &SelectRect, to: &SelectRect) {
+        let scale = |s0: i32, e0: i32, s1: i32, e1: i32| {
+            if e0 > s0 {
+                (e1 - s1) as f64 / (e0 - s0) as f64
+            } else {
+                1.0
+            }
+        };
+        let sx = scale(from.sx, from.ex, to.sx, to.ex);
+        let sy = scale(from.sy, from.ey, to.sy, to.ey);
+        let map = |(x, y): (f64, f64)| {
+            (
+                to.sx as f64 + (x - from.sx as f64) * sx,
+                to.sy as f64 + (y - from.sy as f64) * sy,
+            )
+        };
+
+        if self.step.is_some() {
+            let center = |r: &SelectRect| ((r.sx + r.ex) as f64 / 2.0, (r.sy + r.ey) as f64 / 2.0);
+            let (c0, c1) = (center(from), center(to));
+            self.points = origin
+                .points
+                .iter()
+                .map(|(x, y)| (x + c1.0 - c0.0, y + c1.1 - c0.1))
+                .collect();
+            return;
+        }
+        self.points = origin.points.iter().copied().map(map).collect();
+        if let (Some(content), Some(origin)) = (self.text.as_mut(), origin.text.as_ref()) {
+            content.size = (origin.size * sy).max(1.0);
+        }
+    }
+
+    /// 在 cairo 上下文中绘制，origin 为上下文左上角的全局坐标
+    pub fn draw(&self, cr: &Context, origin: (f64, f64)) {
+        self.draw_with_preedit(cr, origin, None);
+    }
+
+    /// 绘制标注，正在输入的文字标注额外绘制预编辑文字和光标
+    fn draw_with_preedit(&self, cr: &Context, origin: (f64, f64), preedit: Option<&str>) {
+        let points: Vec<(f64, f64)> = self
+            .points
+            .iter()
+            .map(|(x, y)| (x - origin.0, y - origin.1))
+            .collect();
+        let (Some(&(sx, sy)), Some(&(ex, ey))) = (points.first(), points.last()) else {
+            return;
+        };
+
+        cr.save().unwrap();
+        let (r, g, b) = self.style.color;
+        cr.set_source_rgb(r, g, b);
+        cr.set_line_width(self.style.width);
+        cr.set_line_cap(cairo::LineCap::Round);
+        cr.set_line_join(cairo::LineJoin::Round);
+
+        match self.tool {
+            Tool::Rect => {
+                cr.rectangle(sx.min(ex), sy.min(ey), (ex - sx).abs(), (ey - sy).abs());
+            }
+            Tool::Ellipse => {
+                let (rx, ry) = (((ex - sx) / 2.0).abs(), ((ey - sy) / 2.0).abs());
+                if rx > 0.0 && ry > 0.0 {
+                    cr.save().unwrap();
+                    cr.translate((sx + ex) / 2.0, (sy + ey) / 2.0);
+                    cr.scale(rx, ry);
+                    cr.arc(0.0, 0.0, 1.0, 0.0, 2.0 * PI);
+                    cr.restore().unwrap();
+                }
+            }
+            Tool::Line => {
+                cr.move_to(sx, sy);
+                cr.line_to(ex, ey);
+            }
+            Tool::Arrow => {
+                cr.move_to(sx, sy);
+                cr.line_to(ex, ey);
+                cr.stroke().unwrap();
+                self.arrow_head(cr, (sx, sy), (ex, ey));
+            }
+            Tool::Pen => {
+                cr.move_to(sx, sy);
+                for &(x, y) in &points[1..] {
+                    cr.line_to(x, y);
+                }
+            }
+            // 正片叠底只会让底色变暗，下方的文字仍然清晰
+            Tool::Highlighter => {
+                cr.set_operator(cairo::Operator::Multiply);
+                cr.set_line_width(self.style.width * HIGHLIGHT_STRENGTH);
+                cr.set_line_cap(cairo::LineCap::Square);
+                cr.move_to(sx, sy);
+                for &(x, y) in &points[1..] {
+                    cr.line_to(x, y);
+                }
+            }
+            Tool::Step => {
+                if let Some(step) = self.step {
+                    draw_step(cr, (sx, sy), self.step_radius(), step);
+                }
+            }
+            Tool::Text => {
+                if let Some(content) = self.text.as_ref() {
+                    draw_text(cr, (sx, sy), content, preedit);
+                }
+            }
+            Tool::Pixelate | Tool::Blur => {
+                let redaction = if self.tool == Tool::Pixelate {
+                    Redaction::Pixelate
+                } else {
+                    Redaction::Blur
+                };
+                redact::apply(
+                    cr,
+                    (sx, sy, ex, ey),
+                    redaction,
+                    self.style.width * REDACT_STRENGTH,
+                );
+            }
+            Tool::Redact => {
+                cr.rectangle(sx.min(ex), sy.min(ey), (ex - sx).abs(), (ey - sy).abs());
+                cr.fill().unwrap();
+            }
+        }
+        cr.stroke().unwrap();
+        cr.restore().unwrap();
+    }
+
+    /// 箭头尖端为实心三角形，大小随线宽变化
+    fn arrow_head(&self, cr: &Context, start: (f64, f64), end: (f64, f64)) {
+        let angle = (end.1 - start.1).atan2(end.0 - start.0);
+        let length = (self.style.width * 4.0).max(12.0);
+        let spread = PI / 7.0;
+
+        cr.move_to(end.0, end.1);
+        cr.line_to(
+            end.0 - length * (angle - spread).cos(),
+            end.1 - length * (angle - spread).sin(),
+        );
+        cr.line_to(
+            end.0 - length * (angle + spread).cos(),
+            end.1 - length * (angle + spread).sin(),
+        );
+        cr.close_path();
+        cr.fill().unwrap();
+    }
+}
+
+/// 以当前颜色填充圆，圆心处绘制白色的编号
+fn draw_step(cr: &Context, center: (f64, f64), radius: f64, step: u32) {
+    cr.arc(center.0, center.1, radius, 0.0, 2.0 * PI);
+    cr.fill().unwrap();
+
+    let label = step.to_string();
+    cr.select_font_face(
+        "sans-serif",
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Bold,
+    );
+    cr.set_font_size(radius * if label.len() > 2 { 0.8 } else { 1.1 });
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    if let Ok(extents) = cr.text_extents(&label) {
+        cr.move_to(
+            center.0 - extents.x_bearing() - extents.width() / 2.0,
+            center.1 - extents.y_bearing() - extents.height() / 2.0,
+        );
+        cr.show_text(&label).unwrap();
+    }
+}
+
+/// 设置文字标注的字体
+fn set_font(cr: &Context, content: &TextContent) {
+    cr.select_font_face(
+        &content.family,
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Normal,
+    );
+    cr.set_font_size(content.size);
+}
+
+/// 只用于测量文字宽度的最小画布
+fn measure_context() -> Option<Context> {
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 1, 1).ok()?;
+    Context::new(&surface).ok()
+}
+
+fn text_advance(cr: &Context, text: &str) -> f64 {
+    cr.text_extents(text).map(|e| e.x_advance()).unwrap_or(0.0)
+}
+
+/// 逐行绘制文字，preedit 为 Some 时在末尾绘制带下划线的预编辑文字和光标
+fn draw_text(cr: &Context, pos: (f64, f64), content: &TextContent, preedit: Option<&str>) {
+    set_font(cr, content);
+    let line_height = content.size * LINE_HEIGHT;
+    let lines: Vec<&str> = content.text.split('\n').collect();
+
+    let mut width: f64 = 0.0;
+    for (i, line) in lines.iter().enumerate() {
+        cr.move_to(pos.0, pos.1 + content.size + line_height * i as f64);
+        cr.show_text(line).unwrap();
+        width = width.max(text_advance(cr, line));
+    }
+
+    let Some(preedit) = preedit else {
+        return;
+    };
+    // 预编辑文字接在最后一行之后
+    let last = lines.len() - 1;
+    let baseline = pos.1 + content.size + line_height * last as f64;
+    let x = pos.0 + text_advance(cr, lines[last]);
+    cr.move_to(x, baseline);
+    cr.show_text(preedit).unwrap();
+    let caret_x = x + text_advance(cr, preedit);
+    width = width.max(caret_x - pos.0);
+
+    cr.set_line_width(1.0);
+    if !preedit.is_empty() {
+        cr.move_to(x, baseline + 2.0);
+        cr.line_to(caret_x, baseline + 2.0);
+    }
+    cr.move_to(caret_x, baseline - content.size);
+    cr.line_to(caret_x, baseline + content.size * (LINE_HEIGHT - 1.0));
+    cr.stroke().unwrap();
+
+    // 输入框
+    cr.set_dash(&[4.0, 4.0], 0.0);
+    cr.rectangle(
+        pos.0 - 4.0,
+        pos.1 - 4.0,
+        width.max(content.size) + 8.0,
+        line_height * lines.len() as f64 + 8.0,
+    );
+    cr.stroke().unwrap();
+}
+
+/// 正在拖动的选中标注
+#[derive(Debug, Clone)]
+struct ObjectDrag {
+    /// 拖动的控制点，与选区编辑相同
+    handle: EditAction,
+    start: (f64, f64),
+    /// 拖动开始时的标注和边界
+    origin: Annotation,
+    origin_rect: SelectRect,
+    rect: SelectRect,
+}
+
+/// 标注边界对应的选区，用于复用选区的命中检测和编辑逻辑
+fn bounds_rect(annotation: &Annotation) -> SelectRect {
+    let (sx, sy, ex, ey) = annotation.bounds();
+    SelectRect::new(
+        sx.floor() as i32,
+        sy.floor() as i32,
+        ex.ceil() as i32,
+        ey.ceil() as i32,
+    )
+}
+
+/// NOTE: 编辑模式下的标注状态
+#[derive(Debug)]
+pub struct Annotator {
+    pub annotations: Vec<Annotation>,
+    /// 正在绘制的标注
+    pub current: Option<Annotation>,
+    /// 当前选中的工具，None 时拖动用于调整选区
+    pub tool: Option<Tool>,
+    pub style: Style,
+
+    /// 文字标注的字体和字号
+    pub font_family: String,
+    pub font_size: f64,
+    /// 输入法正在编辑的文字
+    pub preedit: String,
+
+    /// 第一个步骤序号的编号
+    pub step_start: u32,
+    /// 下一个步骤序号的编号
+    pub next_step: u32,
+
+    /// 选中的标注
+    pub selected: Option<usize>,
+    drag: Option<ObjectDrag>,
+}
+
+impl Default for Annotator {
+    fn default() -> Self {
+        Self {
+            annotations: Vec::new(),
+            current: None,
+            tool: None,
+            style: Style {
+                color: (1.0, 0.0, 0.0),
+                width: 4.0,
+            },
+            font_family: "sans-serif".to_string(),
+            font_size: 24.0,
+            preedit: String::new(),
+            step_start: 1,
+            next_step: 1,
+            selected: None,
+            drag: None,
+        }
+    }
+}
+
+impl Annotator {
+    pub fn new(style: Style, font_family: String, font_size: f64, step_start: u32) -> Self {
+        Self {
+            style,
+            font_family,
+            font_size,
+            step_start,
+            next_step: step_start,
+            ..Default::default()
+        }
+    }
+
+    /// 是否放置过步骤序号
+    pub fn has_steps(&self) -> bool {
+        self.annotations.iter().any(|a| a.step.is_some())
+    }
+
+    /// 按放置顺序从 step_start 开始重新编号
+    pub fn renumber_steps(&mut self) {
+        let mut next = self.step_start;
+        for annotation in self.annotations.iter_mut() {
+            if let Some(step) = annotation.step.as_mut() {
+                *step = next;
+                next += 1;
+            }
+        }
+        self.next_step = next;
+    }
+
+    /// 在 pos 处放置新的文字框
+    pub fn begin_text(&mut self, pos: (f64, f64)) {
+        self.current = Some(Annotation::new_text(
+            pos,
+            self.style,
+            &self.font_family,
+            self.font_size,
+        ));
+        self.preedit.clear();
+    }
+
+    /// 正在输入的文字，不是文字标注时返回 None
+    pub fn editing_text(&mut self) -> Option<&mut String> {
+        self.current
+            .as_mut()
+            .and_then(|a| a.text.as_mut())
+            .map(|c| &mut c.text)
+    }
+
+    /// 文字光标的全局位置和高度，用于告知输入法候选框的位置
+    pub fn text_caret(&self) -> Option<(f64, f64, f64)> {
+        let current = self.current.as_ref()?;
+        let content = current.text.as_ref()?;
+        let &(x, y) = current.points.first()?;
+
+        let cr = measure_context()?;
+        set_font(&cr, content);
+        let lines: Vec<&str> = content.text.split('\n').collect();
+        let last = lines.len() - 1;
+        let caret_x = x + text_advance(&cr, &format!("{}{}", lines[last], self.preedit));
+        let line_height = content.size * LINE_HEIGHT;
+        Some((caret_x, y + line_height * last as f64, line_height))
+    }
+
+    /// 全局坐标处的标注及对应的编辑行为，选中的标注可以拖动控制点缩放，其他标注只能移动
+    pub fn object_at(&self, pos: (f64, f64), threshold: i32) -> Option<(usize, EditAction)> {
+        let (gx, gy) = (pos.0 as i32, pos.1 as i32);
+        if let Some(index) = self.selected
+            && let Some(annotation) = self.annotations.get(index)
+        {
+            match bounds_rect(annotation).hit_region(gx, gy, threshold) {
+                EditAction::None => {}
+                handle => return Some((index, handle)),
+            }
+        }
+        // 后绘制的标注在上层，优先命中
+        self.annotations
+            .iter()
+            .rposition(|a| bounds_rect(a).hit_region(gx, gy, threshold) != EditAction::None)
+            .map(|index| (index, EditAction::Move))
+    }
+
+    /// 选中标注并开始拖动
+    pub fn begin_drag(&mut self, index: usize, handle: EditAction, pos: (f64, f64)) {
+        let Some(annotation) = self.annotations.get(index) else {
+            return;
+        };
+        let rect = bounds_rect(annotation);
+        self.selected = Some(index);
+        self.drag = Some(ObjectDrag {
+            handle,
+            start: pos,
+            origin: annotation.clone(),
+            origin_rect: rect.clone(),
+            rect,
+        });
+    }
+
+    pub fn update_drag(&mut self, pos: (f64, f64)) {
+        let (Some(drag), Some(index)) = (self.drag.as_mut(), self.selected) else {
+            return;
+        };
+        // 拖过对边时控制点会翻转，与选区编辑一致
+        if let Action::OnEdit(handle) = drag.rect.edit(drag.start, pos, Action::OnEdit(drag.handle))
+        {
+            drag.handle = handle;
+        }
+        if let Some(annotation) = self.annotations.get_mut(index) {
+            annotation.map_rect(&drag.origin, &drag.origin_rect, &drag.rect);
+        }
+    }
+
+    pub fn finish_drag(&mut self) {
+        self.drag = None;
+    }
+
+    /// 删除选中的标注，删除步骤序号后重新编号
+    pub fn delete_selected(&mut self) -> bool {
+        let Some(index) = self.selected.take() else {
+            return false;
+        };
+        if index >= self.annotations.len() {
+            return false;
+        }
+        if self.annotations.remove(index).step.is_some() {
+            self.renumber_steps();
+        }
+        true
+    }
+
+    /// 撤销或重做后恢复标注，清除选中状态
+    pub fn restore(&mut self, annotations: Vec<Annotation>) {
+        self.annotations = annotations;
+        self.selected = None;
+        self.drag = None;
+        self.renumber_steps();
+    }
+
+    /// 开始绘制，步骤序号工具点击已有的序号时删除它并重新编号
+    pub fn begin(&mut self, pos: (f64, f64)) {
+        match self.tool {
+            Some(Tool::Step) => {
+                if let Some(index) = self.annotations.iter().rposition(|a| a.step_contains(pos)) {
+                    self.annotations.remove(index);
+                    self.renumber_steps();
+                } else {
+                    self.current = Some(Annotation::new_step(pos, self.style, self.next_step));
+                    self.next_step += 1;
+                }
+            }
+            Some(tool) => self.current = Some(Annotation::new(tool, pos, self.style)),
+            None => {}
+        }
+    }
+
+    pub fn update(&mut self, pos: (f64, f64)) {
+        if let Some(current) = self.current.as_mut() {
+            current.update(pos);
+        }
+    }
+
+    /// 结束绘制，忽略没有拖动的标注
+    pub fn finish(&mut self) {
+        self.preedit.clear();
+        if let Some(current) = self.current.take()
+            && !current.is_empty()
+        {
+            self.annotations.push(current);
+        }
+    }
+
+    /// 绘制选中标注的边框和控制点，只用于预览
+    pub fn draw_selection(&self, cr: &Context, origin: (f64, f64)) {
+        let Some(annotation) = self.selected.and_then(|i| self.annotations.get(i)) else {
+            return;
+        };
+        let rect = bounds_rect(annotation);
+        let (sx, sy) = (rect.sx as f64 - origin.0, rect.sy as f64 - origin.1);
+        let (ex, ey) = (rect.ex as f64 - origin.0, rect.ey as f64 - origin.1);
+
+        cr.save().unwrap();
+        cr.set_line_width(1.0);
+        cr.set_source_rgb(0.3, 0.5, 0.9);
+        cr.set_dash(&[4.0, 4.0], 0.0);
+        cr.rectangle(sx, sy, ex - sx, ey - sy);
+        cr.stroke().unwrap();
+
+        cr.set_dash(&[], 0.0);
+        let (mx, my) = ((sx + ex) / 2.0, (sy + ey) / 2.0);
+        for (x, y) in [
+            (sx, sy),
+            (mx, sy),
+            (ex, sy),
+            (sx, my),
+            (ex, my),
+            (sx, ey),
+            (mx, ey),
+            (ex, ey),
+        ] {
+            cr.rectangle(x - 4.0, y - 4.0, 8.0, 8.0);
+        }
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.fill_preserve().unwrap();
+        cr.set_source_rgb(0.3, 0.5, 0.9);
+        cr.stroke().unwrap();
+        cr.restore().unwrap();
+    }
+
+    /// 只绘制遮挡或只绘制其他标注，用于导出矢量图时分层绘制
+    pub fn draw_layer(&self, cr: &Context, origin: (f64, f64), redactions: bool) {
+        for annotation in &self.annotations {
+            if annotation.tool.is_redaction() == redactions {
+                annotation.draw(cr, origin);
+            }
+        }
+    }
+
+    /// 没有冻结画面时马赛克和模糊没有可处理的像素，改为绘制带斜线的虚线框作为预览
+    pub fn draw_redaction_placeholders(&self, cr: &Context, origin: (f64, f64)) {
+        let redactions = self
+            .annotations
+            .iter()
+            .chain(self.current.as_ref())
+            .filter(|a| matches!(a.tool, Tool::Pixelate | Tool::Blur));
+        for annotation in redactions {
+            let (sx, sy, ex, ey) = annotation.bounds();
+            let (x, y) = (sx - origin.0, sy - origin.1);
+            let (w, h) = (ex - sx, ey - sy);
+
+            cr.save().unwrap();
+            let (r, g, b) = annotation.style.color;
+            cr.set_source_rgba(r, g, b, 0.8);
+            cr.set_line_width(1.0);
+            cr.rectangle(x, y, w, h);
+            cr.clip();
+            let mut offset = 0.0;
+            while offset < w + h {
+                cr.move_to(x + offset, y);
+                cr.line_to(x + offset - h, y + h);
+                offset += 8.0;
+            }
+            cr.stroke().unwrap();
+            cr.reset_clip();
+            cr.set_dash(&[4.0, 4.0], 0.0);
+            cr.rectangle(x, y, w, h);
+            cr.stroke().unwrap();
+            cr.restore().unwrap();
+        }
+    }
+
+    /// 绘制所有标注，origin 为上下文左上角的全局坐标
+    pub fn draw(&self, cr: &Context, origin: (f64, f64)) {
+        for annotation in &self.annotations {
+            annotation.draw(cr, origin);
+        }
+        if let Some(current) = self.current.as_ref() {
+            current.draw_with_preedit(cr, origin, Some(&self.preedit));
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use clap::ValueEnum;
+
+use crate::wayland_ctx::WaylandCtx;
+
+/// 复制到剪贴板的颜色格式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorFormat {
+    #[default]
+    Hex,
+    Rgb,
+    Hsl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PickedColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl PickedColor {
+    pub fn to_hex(self) -> String {
+        format!("#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+
+    pub fn to_rgb(self) -> String {
+        format!("rgb({}, {}, {})", self.r, self.g, self.b)
+    }
+
+    pub fn to_hsl(self) -> String {
+        let (r, g, b) = (
+            self.r as f64 / 255.0,
+            self.g as f64 / 255.0,
+            self.b as f64 / 255.0,
+        );
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let d = max - min;
+
+        let (h, s) = if d == 0.0 {
+            (0.0, 0.0)
+        } else {
+            let s = d / (1.0 - (2.0 * l - 1.0).abs());
+            let h = if max == r {
+                60.0 * (((g - b) / d).rem_euclid(6.0))
+            } else if max == g {
+                60.0 * ((b - r) / d + 2.0)
+            } else {
+                60.0 * ((r - g) / d + 4.0)
+            };
+            (h, s)
+        };
+
+        format!(
+            "hsl({}, {}%, {}%)",
+            h.round() as i32,
+            (s * 100.0).round() as i32,
+            (l * 100.0).round() as i32
+        )
+    }
+
+    pub fn format(self, format: ColorFormat) -> String {
+        match format {
+            ColorFormat::Hex => self.to_hex(),
+            ColorFormat::Rgb => self.to_rgb(),
+            ColorFormat::Hsl => self.to_hsl(),
+        }
+    }
+}
+
+/// 取色结果，单击时 samples 为 1，拖动时为区域内的平均色
+#[derive(Debug, Clone, Copy)]
+pub struct PickResult {
+    pub color: PickedColor,
+    pub samples: usize,
+}
+
+/// 从 base_canvas 读取全局逻辑坐标对应的像素，按缩放换算到物理像素
+pub fn sample_pixel(wl_ctx: &WaylandCtx, gx: i32, gy: i32) -> Option<(u8, u8, u8)> {
+    let outputs = wl_ctx.foam_outputs.as_ref()?;
+    let canvases = wl_ctx.scm.base_canvas.as_ref()?;
+
+    for output in outputs {
+        let (lw, lh) = output.logical_size();
+        if gx < output.global_x
+            || gy < output.global_y
+            || gx >= output.global_x + lw
+            || gy >= output.global_y + lh
+        {
+            continue;
+        }
+        let (fx, fy) = output.physical_factor();
+        let px = (((gx - output.global_x) as f64 * fx) as i32).clamp(0, output.width - 1);
+        let py = (((gy - output.global_y) as f64 * fy) as i32).clamp(0, output.height - 1);
+
+        let canvas = canvases.get(&output.id)?;
+        let offset = (py as usize * output.width as usize + px as usize) * 4;
+        // ARGB8888 小端存储：[B, G, R, A]
+        let pixel = canvas.get(offset..offset + 4)?;
+        return Some((pixel[2], pixel[1], pixel[0]));
+    }
+    None
+}
+
+/// 计算全局选区内的平均色，选区为空时读取光标下的像素
+pub fn pick(wl_ctx: &WaylandCtx) -> Option<PickResult> {
+    let (sx, sy, ex, ey) = match wl_ctx.global_rect.as_ref() {
+        Some(rect) if rect.ex - rect.sx > 1 || rect.ey - rect.sy > 1 => {
+            (rect.sx, rect.sy, rect.ex, rect.ey)
+        }
+        _ => {
+            let (x, y) = wl_ctx
+                .pointer_helper
+                .g_end_pos
+                .or(wl_ctx.pointer_helper.g_current_pos)?;
+            (x as i32, y as i32, x as i32 + 1, y as i32 + 1)
+        }
+    };
+
+    let (mut r, mut g, mut b, mut n) = (0u64, 0u64, 0u64, 0usize);
+    for y in sy..ey {
+        for x in sx..ex {
+            if let Some((pr, pg, pb)) = sample_pixel(wl_ctx, x, y) {
+                r += pr as u64;
+                g += pg as u64;
+                b += pb as u64;
+                n += 1;
+            }
+        }
+    }
+    if n == 0 {
+        return None;
+    }
+
+    let avg = |v: u64| (v as f64 / n as f64).round() as u8;
+    Some(PickResult {
+        color: PickedColor {
+            r: avg(r),
+            g: avg(g),
+            b: avg(b),
+        },
+        samples: n,
+    })
+}
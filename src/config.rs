@@ -1,12 +1,18 @@
 use chrono::Local;
-use clap::Parser;
-use directories::UserDirs;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use directories::{ProjectDirs, UserDirs};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::color_picker::ColorFormat;
 
 #[derive(Parser)]
 #[command(version, long_about = None)]
 struct CliArgs {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+
     /// The directory path where the output file is located. The default is the XDG user image path
     #[arg(short = 'p', long)]
     path: Option<PathBuf>,
@@ -15,6 +21,10 @@ struct CliArgs {
     #[arg(short = 'n', long, default_value_t = Self::default_name())]
     name: String,
 
+    /// Output format, overrides the extension of the output file name. svg and pdf keep annotations as vector paths and text
+    #[arg(long, value_enum)]
+    format: Option<ImageType>,
+
     /// Whether to display the mouse when taking a screenshot. The default value is false
     #[arg(long, default_value_t = false)]
     show_cursor: bool,
@@ -38,6 +48,192 @@ struct CliArgs {
     /// disable desktop notify, the default is false
     #[arg(long, default_value_t = false)]
     no_notify: bool,
+
+    /// Distance in pixels within which selection edges snap to output edges and the last selection, 0 disables snapping. Hold Alt to temporarily disable it
+    #[arg(long, default_value_t = 8)]
+    snap_threshold: i32,
+
+    /// Capture the region saved by the last screenshot without interaction
+    #[arg(long, default_value_t = false)]
+    last_region: bool,
+
+    /// Save every region as its own file instead of one composite image
+    #[arg(long, default_value_t = false)]
+    split_regions: bool,
+
+    /// Background of the composite image outside the selected regions, `transparent` or a color like `#ffffff`
+    #[arg(long, default_value = "transparent", value_parser = parse_background)]
+    region_background: Background,
+
+    /// Pointer movement in pixels below which a press and release counts as a click that captures the window or output under the pointer
+    #[arg(long, default_value_t = 4.0)]
+    min_drag: f64,
+
+    /// What a click without dragging captures
+    #[arg(long, value_enum, default_value_t = ClickTarget::Auto)]
+    click_target: ClickTarget,
+
+    /// Color format copied to the clipboard in pick-color mode
+    #[arg(long, value_enum, default_value_t = ColorFormat::Hex)]
+    color_format: ColorFormat,
+
+    /// Default color of annotations drawn in edit mode, like `#ff0000`
+    #[arg(long, default_value = "#ff0000", value_parser = parse_color)]
+    stroke_color: (f64, f64, f64),
+
+    /// Default line width of annotations drawn in edit mode
+    #[arg(long, default_value_t = 4.0)]
+    stroke_width: f64,
+
+    /// Font family of text annotations, pick one that covers your language such as `Noto Sans CJK SC`
+    #[arg(long, default_value = "sans-serif")]
+    font_family: String,
+
+    /// Font size of text annotations
+    #[arg(long, default_value_t = 24.0)]
+    font_size: f64,
+
+    /// Number of the first step marker
+    #[arg(long, conflicts_with = "continue_steps")]
+    step_start: Option<u32>,
+
+    /// Continue step marker numbering from the last saved screenshot that used step markers
+    #[arg(long, default_value_t = false)]
+    continue_steps: bool,
+
+    /// Pin the capture as a floating always-on-top window instead of saving it. Drag to move, scroll to zoom, Ctrl+scroll to change the opacity, Esc or double-click to close
+    #[arg(long, default_value_t = false)]
+    pin: bool,
+
+    /// Initial opacity of the pinned window, from 0.2 to 1
+    #[arg(long, default_value_t = 1.0)]
+    pin_opacity: f64,
+
+    /// Show an action bar above the selection in edit mode with Save, Save as, Copy, Pin, Run hook and Discard buttons. The capture is only written to disk when the chosen action needs it
+    #[arg(long, default_value_t = false)]
+    action_bar: bool,
+
+    /// Run a command after saving, `{path}` is replaced with the quoted file path. Capture details are passed in FOAMSHOT_* environment variables. Can be repeated
+    #[arg(long, value_name = "COMMAND")]
+    exec: Vec<String>,
+
+    /// Run a named hook from `$XDG_CONFIG_HOME/foamshot/config.json` after saving, like `{"hooks": {"upload": "curl -F file=@{path} ..."}}`. Can be repeated
+    #[arg(long, value_name = "NAME")]
+    hook: Vec<String>,
+
+    /// Wait for the hooks to finish and show their output in the save notification. Without it hooks run in the background and outlive foamshot
+    #[arg(long, default_value_t = false)]
+    wait_hooks: bool,
+
+    /// Seconds to wait for the hooks with `--wait-hooks`, hooks still running after that are killed and reported as failed
+    #[arg(long, default_value_t = 30)]
+    hook_timeout: u64,
+
+    /// Do not embed capture metadata (creation time, region, output names and scales, window app id and title) in PNG and JPEG files
+    #[arg(long, default_value_t = false)]
+    no_metadata: bool,
+
+    /// Seconds the Open, Open folder, Copy again and Delete buttons of the save notification keep working. A background process handles them so foamshot itself exits right away, 0 disables them
+    #[arg(long, default_value_t = 10)]
+    notify_timeout: u64,
+
+    /// Print the result as one JSON object on stdout when exiting: status, path, format, byte size, global rect, per-output rects and timing. The exit code is 1 on error, 2 when cancelled and 0 otherwise
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
+    /// Also save a `.foam` project next to the image, keeping the raw capture, the crop and the annotations editable with `foamshot edit`
+    #[arg(long, default_value_t = false)]
+    project: bool,
+}
+
+/// 合成图片背景色，None 为透明
+#[derive(Debug, Clone, Copy)]
+struct Background(Option<(f64, f64, f64)>);
+
+/// 解析合成图片背景色
+fn parse_background(value: &str) -> Result<Background, String> {
+    if value.eq_ignore_ascii_case("transparent") {
+        return Ok(Background(None));
+    }
+    parse_color(value).map(|color| Background(Some(color)))
+}
+
+/// 解析 `#rrggbb` 格式的颜色
+fn parse_color(value: &str) -> Result<(f64, f64, f64), String> {
+    let hex = value.trim_start_matches('#');
+    // 非 ASCII 字符会使按字节切片越过字符边界
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("invalid color: {}", value));
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .map(|v| v as f64 / 255.0)
+            .map_err(|_| format!("invalid color: {}", value))
+    };
+    Ok((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Click a pixel or drag a small rectangle to print its (average) color as hex, rgb() and hsl()
+    PickColor,
+    /// Drag rectangles, or lines while holding Shift, on the frozen screen to measure their size and angle without taking a screenshot
+    Measure,
+    /// Reopen a `.foam` project to change the crop or annotations and export again. Outputs are matched by name and size, so a moved or rescaled monitor still works
+    Edit {
+        /// The `.foam` project file
+        file: PathBuf,
+    },
+    /// Browse the captures recorded in `$XDG_DATA_HOME/foamshot/history.jsonl`
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Send the save notification and handle its buttons, started in the background after saving
+    #[command(hide = true)]
+    NotifySaved(NotifySavedArgs),
+}
+
+/// 后台处理保存通知的参数
+#[derive(Args, Debug, Clone)]
+pub struct NotifySavedArgs {
+    /// 通知正文
+    #[arg(long)]
+    pub body: String,
+    /// 按钮保持有效的秒数
+    #[arg(long)]
+    pub timeout: u64,
+    /// 本次保存的文件，格式由后缀识别
+    pub files: Vec<PathBuf>,
+}
+
+/// `foamshot history` 的子命令，编号从最新的截图开始为 1
+#[derive(Subcommand, Debug, Clone)]
+pub enum HistoryCommand {
+    /// List the recorded captures, newest first. Captures whose file is gone are marked missing
+    List {
+        /// Only list the newest N captures
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+    /// Open a capture with xdg-open, the newest one by default
+    Open { id: Option<usize> },
+    /// Copy a capture to the clipboard again, the newest one by default. Requires wl-copy
+    CopyAgain { id: Option<usize> },
+    /// Delete the file of a capture and its history entry, the newest one by default
+    Delete { id: Option<usize> },
+    /// Drop history entries older than some days or beyond the newest N. Without options the `history` retention policy of the config file is used
+    Prune {
+        /// Drop entries older than this many days
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u32>,
+        /// Keep only the newest N entries
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+        /// Also delete the files of the dropped entries
+        #[arg(long, default_value_t = false)]
+        delete_files: bool,
+    },
 }
 
 impl CliArgs {
@@ -46,16 +242,58 @@ impl CliArgs {
     }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum ImageType {
     #[default]
     Png,
     Jpg,
+    Svg,
+    Pdf,
+}
+
+impl ImageType {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageType::Png => "png",
+            ImageType::Jpg => "jpg",
+            ImageType::Svg => "svg",
+            ImageType::Pdf => "pdf",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageType::Png => "image/png",
+            ImageType::Jpg => "image/jpeg",
+            ImageType::Svg => "image/svg+xml",
+            ImageType::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// 单击时截取的对象
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClickTarget {
+    /// Window under the pointer when the compositor exposes window information, the output otherwise
+    #[default]
+    Auto,
+    /// Always the output under the pointer
+    Output,
+}
+
+/// 运行模式，由子命令决定
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    #[default]
+    Shot,
+    PickColor,
+    Measure,
 }
 
 #[derive(Debug)]
 #[allow(unused)]
 pub struct FoamConfig {
+    pub mode: Mode,
     /// 输出路径
     pub output_path: PathBuf,
     /// 输出类型，默认为 png
@@ -73,6 +311,66 @@ pub struct FoamConfig {
     pub full_screen: bool,
 
     pub allow_notify: bool,
+    /// 退出前等待保存通知按钮的时间
+    pub notify_timeout: Duration,
+
+    /// 是否直接截取上一次保存的区域
+    pub last_region: bool,
+
+    /// 多个选区时是否每个选区保存为单独的文件
+    pub split_regions: bool,
+    /// 合成图片中选区外的背景色，None 为透明
+    pub region_background: Option<(f64, f64, f64)>,
+
+    /// 小于该距离的按下-松开视为单击
+    pub min_drag: f64,
+    /// 单击时截取的对象
+    pub click_target: ClickTarget,
+
+    /// 选区磁吸距离，为 0 时关闭
+    pub snap_threshold: i32,
+    /// 取色结果复制到剪贴板的格式
+    pub color_format: ColorFormat,
+
+    /// 标注的默认颜色
+    pub stroke_color: (f64, f64, f64),
+    /// 标注的默认线宽
+    pub stroke_width: f64,
+    /// 文字标注的字体
+    pub font_family: String,
+    /// 文字标注的字号
+    pub font_size: f64,
+    /// 第一个步骤序号的编号，None 时从 1 开始
+    pub step_start: Option<u32>,
+    /// 是否接着上一次保存的步骤序号继续编号
+    pub continue_steps: bool,
+
+    /// 截图后钉在屏幕上而不是保存
+    pub pin: bool,
+    /// 钉图窗口的初始不透明度
+    pub pin_opacity: f64,
+
+    /// 编辑模式下是否显示截图操作栏
+    pub action_bar: bool,
+
+    /// 保存后运行的命令
+    pub exec: Vec<String>,
+    /// 保存后运行的命名钩子
+    pub hooks: Vec<String>,
+    /// 是否等待钩子结束，并把输出放入保存通知
+    pub wait_hooks: bool,
+    /// 等待钩子的最长时间
+    pub hook_timeout: Duration,
+
+    /// 是否在 PNG 和 JPEG 文件中写入截图信息
+    pub metadata: bool,
+    /// 退出时是否在 stdout 输出 JSON 结果
+    pub json: bool,
+
+    /// 要重新编辑的项目文件
+    pub project_input: Option<PathBuf>,
+    /// 保存时同时写入的项目文件
+    pub project_output: Option<PathBuf>,
 }
 
 impl Default for FoamConfig {
@@ -88,23 +386,104 @@ impl FoamConfig {
         // 构造完整的输出路径
         let formatted_path =
             Self::format_path(args.path.unwrap_or(Self::generate_default_output_path()));
-        let formatted_name = Self::replace_time_specifiers(&args.name);
+        let mut formatted_name = Self::replace_time_specifiers(&args.name);
+        // 指定格式时替换文件名的后缀
+        if let Some(format) = args.format {
+            formatted_name = Path::new(&formatted_name)
+                .with_extension(format.extension())
+                .to_string_lossy()
+                .to_string();
+        }
         let (final_path, final_name) = Self::validate_path(&formatted_path, &formatted_name);
         let mut output_path = final_path;
         output_path.push(final_name);
 
         let image_type = Self::detect_image_type(&mut output_path);
 
+        let (mode, project_input) = match args.command {
+            Some(CliCommand::PickColor) => (Mode::PickColor, None),
+            Some(CliCommand::Measure) => (Mode::Measure, None),
+            Some(CliCommand::Edit { file }) => (Mode::Shot, Some(file)),
+            // history 和 notify-saved 子命令在连接 wayland 之前已经处理
+            Some(CliCommand::History { .. } | CliCommand::NotifySaved(_)) | None => {
+                (Mode::Shot, None)
+            }
+        };
+        // 重新编辑时保存回原项目文件
+        let project_output = match project_input.as_ref() {
+            Some(file) => Some(file.clone()),
+            None => args.project.then(|| output_path.with_extension("foam")),
+        };
+
         FoamConfig {
+            mode,
             output_path,
             image_type,
             cursor: args.show_cursor,
-            edit: args.edit,
+            // 项目文件直接进入编辑模式
+            edit: args.edit || project_input.is_some(),
             auto_copy: !args.no_copy,
-            freeze: !args.no_freeze,
+            // 测量需要读取冻结的画面
+            freeze: !args.no_freeze || mode == Mode::Measure || project_input.is_some(),
             full_screen: args.full_screen,
             allow_notify: !args.no_notify,
+            notify_timeout: Duration::from_secs(args.notify_timeout),
+            last_region: args.last_region,
+            split_regions: args.split_regions,
+            region_background: args.region_background.0,
+            min_drag: args.min_drag,
+            click_target: args.click_target,
+            snap_threshold: args.snap_threshold.max(0),
+            color_format: args.color_format,
+            stroke_color: args.stroke_color,
+            stroke_width: args.stroke_width.max(1.0),
+            font_family: args.font_family,
+            font_size: args.font_size.max(1.0),
+            step_start: args.step_start,
+            continue_steps: args.continue_steps,
+            pin: args.pin,
+            pin_opacity: args.pin_opacity.clamp(0.2, 1.0),
+            action_bar: args.action_bar,
+            exec: args.exec,
+            hooks: args.hook,
+            wait_hooks: args.wait_hooks,
+            hook_timeout: Duration::from_secs(args.hook_timeout),
+            metadata: !args.no_metadata,
+            json: args.json,
+            project_input,
+            project_output,
+        }
+    }
+
+    /// `foamshot history` 子命令，不需要连接 wayland
+    pub fn history_command() -> Option<HistoryCommand> {
+        match CliArgs::parse().command {
+            Some(CliCommand::History { command }) => Some(command),
+            _ => None,
+        }
+    }
+
+    /// 保存后在后台运行的 `foamshot notify-saved`
+    pub fn notify_saved_command() -> Option<NotifySavedArgs> {
+        match CliArgs::parse().command {
+            Some(CliCommand::NotifySaved(args)) => Some(args),
+            _ => None,
+        }
+    }
+
+    /// NOTE: 配置文件位于 `$XDG_CONFIG_HOME/foamshot/config.json`，钩子和历史记录等从中读取各自的部分
+    pub fn config_file_path() -> Option<PathBuf> {
+        let dirs = ProjectDirs::from("", "", env!("CARGO_PKG_NAME"))?;
+        Some(dirs.config_dir().join("config.json"))
+    }
+
+    /// 另存为时替换输出路径，按新的后缀识别格式，项目文件跟随图片改名
+    pub fn set_output_path(&mut self, mut path: PathBuf) {
+        self.image_type = Self::detect_image_type(&mut path);
+        if self.project_input.is_none() && self.project_output.is_some() {
+            self.project_output = Some(path.with_extension("foam"));
         }
+        self.output_path = path;
     }
 
     fn format_path(path: PathBuf) -> PathBuf {
@@ -180,6 +559,8 @@ impl FoamConfig {
             match ext.to_lowercase().as_str() {
                 "jpg" | "jpeg" => ImageType::Jpg,
                 "png" => ImageType::Png,
+                "svg" => ImageType::Svg,
+                "pdf" => ImageType::Pdf,
                 _ => {
                     // 如果无法识别后缀，将其设置为 .png
                     if let Some(parent) = path.parent() {
@@ -0,0 +1,118 @@
+use cairo::Context;
+
+const ITEM_WIDTH: f64 = 120.0;
+const ITEM_HEIGHT: f64 = 28.0;
+const FONT_SIZE: f64 = 14.0;
+
+/// 右键菜单和操作栏中的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Save,
+    /// 输入文件名后保存
+    SaveAs,
+    Copy,
+    Pin,
+    /// 保存后运行钩子命令
+    Hook,
+    Discard,
+}
+
+impl MenuItem {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuItem::Save => "Save",
+            MenuItem::SaveAs => "Save as",
+            MenuItem::Copy => "Copy",
+            MenuItem::Pin => "Pin",
+            MenuItem::Hook => "Run hook",
+            MenuItem::Discard => "Discard",
+        }
+    }
+
+    /// 操作栏中显示的快捷键
+    pub fn shortcut(self) -> &'static str {
+        match self {
+            MenuItem::Save => "S",
+            MenuItem::SaveAs => "⇧S",
+            MenuItem::Copy => "C",
+            MenuItem::Pin => "P",
+            MenuItem::Hook => "H",
+            MenuItem::Discard => "Q",
+        }
+    }
+}
+
+/// NOTE: 编辑模式下右键打开的菜单，绘制在点击所在输出的遮罩层上
+#[derive(Debug, Clone)]
+pub struct ContextMenu {
+    pub output_id: usize,
+    /// 菜单左上角的全局坐标
+    pub x: f64,
+    pub y: f64,
+    pub items: Vec<MenuItem>,
+    pub hovered: Option<usize>,
+}
+
+impl ContextMenu {
+    pub fn new(output_id: usize, x: f64, y: f64) -> Self {
+        Self {
+            output_id,
+            x,
+            y,
+            items: vec![
+                MenuItem::Save,
+                MenuItem::Copy,
+                MenuItem::Pin,
+                MenuItem::Discard,
+            ],
+            hovered: None,
+        }
+    }
+
+    /// 返回全局坐标处的菜单项索引
+    pub fn item_at(&self, gx: f64, gy: f64) -> Option<usize> {
+        if gx < self.x || gx > self.x + ITEM_WIDTH || gy < self.y {
+            return None;
+        }
+        let index = ((gy - self.y) / ITEM_HEIGHT) as usize;
+        (index < self.items.len()).then_some(index)
+    }
+
+    /// 更新悬停项，返回是否需要重绘
+    pub fn hover(&mut self, gx: f64, gy: f64) -> bool {
+        let hovered = self.item_at(gx, gy);
+        let changed = hovered != self.hovered;
+        self.hovered = hovered;
+        changed
+    }
+
+    /// 在输出的 cairo 上下文中绘制，origin 为输出左上角的全局坐标
+    pub fn draw(&self, cr: &Context, origin: (i32, i32)) {
+        let x = self.x - origin.0 as f64;
+        let y = self.y - origin.1 as f64;
+
+        cr.save().unwrap();
+        cr.set_source_rgba(0.15, 0.15, 0.15, 0.95);
+        cr.rectangle(x, y, ITEM_WIDTH, ITEM_HEIGHT * self.items.len() as f64);
+        cr.fill().unwrap();
+
+        cr.select_font_face(
+            "sans-serif",
+            cairo::FontSlant::Normal,
+            cairo::FontWeight::Normal,
+        );
+        cr.set_font_size(FONT_SIZE);
+        for (i, item) in self.items.iter().enumerate() {
+            let item_y = y + ITEM_HEIGHT * i as f64;
+            if self.hovered == Some(i) {
+                cr.set_source_rgba(0.3, 0.5, 0.9, 1.0);
+                cr.rectangle(x, item_y, ITEM_WIDTH, ITEM_HEIGHT);
+                cr.fill().unwrap();
+            }
+            cr.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+            cr.move_to(x + 12.0, item_y + ITEM_HEIGHT / 2.0 + FONT_SIZE / 3.0);
+            cr.show_text(item.label()).unwrap();
+        }
+        cr.restore().unwrap();
+    }
+}
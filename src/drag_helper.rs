@@ -0,0 +1,199 @@
+use std::error::Error;
+use std::io::Write;
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use cairo::{Format as CairoFormat, ImageSurface};
+use log::warn;
+use smithay_client_toolkit::shm::{
+    Shm,
+    slot::{Buffer, SlotPool},
+};
+use wayland_client::{
+    Proxy, QueueHandle,
+    protocol::{
+        wl_compositor, wl_data_device, wl_data_device_manager, wl_data_source, wl_seat,
+        wl_shm::Format, wl_surface,
+    },
+};
+
+use crate::foamshot::FoamShot;
+
+/// 拖动图标缩略图的最大边长
+const THUMBNAIL_SIZE: f64 = 128.0;
+
+pub const MIME_PNG: &str = "image/png";
+pub const MIME_URI_LIST: &str = "text/uri-list";
+
+/// 拖动图标 surface 的 user data
+#[derive(Debug, Clone, Copy)]
+pub struct DragIconSurface;
+
+/// NOTE: 基于 wl_data_device 的拖放源，把截图拖到浏览器、聊天窗口等其他程序中
+#[derive(Default)]
+pub struct DragHelper {
+    pub manager: Option<(wl_data_device_manager::WlDataDeviceManager, u32)>,
+    pub data_device: Option<wl_data_device::WlDataDevice>,
+
+    pub source: Option<wl_data_source::WlDataSource>,
+    icon: Option<wl_surface::WlSurface>,
+    /// 图标的 buffer 需要在拖动期间保持存在
+    icon_buffer: Option<(SlotPool, Buffer)>,
+
+    /// 拖放目标请求时发送的数据，发送线程共享同一份 PNG
+    png: Arc<Vec<u8>>,
+    uri_list: Option<String>,
+
+    /// 为拖放写入的文件，拖放取消时删除
+    pub created_file: Option<PathBuf>,
+}
+
+impl DragHelper {
+    /// seat 和 manager 都绑定后创建 data_device
+    pub fn init(&mut self, seat: Option<&wl_seat::WlSeat>, qh: &QueueHandle<FoamShot>) {
+        if self.data_device.is_some() {
+            return;
+        }
+        if let (Some((manager, _)), Some(seat)) = (self.manager.as_ref(), seat) {
+            self.data_device = Some(manager.get_data_device(seat, qh, ()));
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// 以 image 开始拖动，file 存在时同时提供指向它的 uri-list
+    ///
+    /// serial 需要是按下按键时的序列号，origin 为按下时光标所在的 surface
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(
+        &mut self,
+        compositor: &wl_compositor::WlCompositor,
+        shm: &Shm,
+        qh: &QueueHandle<FoamShot>,
+        origin: &wl_surface::WlSurface,
+        serial: u32,
+        image: &ImageSurface,
+        file: Option<&Path>,
+    ) -> Result<(), Box<dyn Error>> {
+        let (Some((manager, _)), Some(data_device)) =
+            (self.manager.as_ref(), self.data_device.as_ref())
+        else {
+            return Err("合成器不支持 wl_data_device_manager".into());
+        };
+
+        let mut png = Vec::new();
+        image
+            .write_to_png(&mut png)
+            .map_err(|e| format!("编码PNG失败: {}", e))?;
+        self.png = Arc::new(png);
+        self.uri_list = file.map(file_uri).map(|uri| format!("{}\r\n", uri));
+
+        let source = manager.create_data_source(qh, ());
+        source.offer(MIME_PNG.to_string());
+        if self.uri_list.is_some() {
+            source.offer(MIME_URI_LIST.to_string());
+        }
+        if source.version() >= 3 {
+            source.set_actions(wl_data_device_manager::DndAction::Copy);
+        }
+
+        let icon = compositor.create_surface(qh, DragIconSurface);
+        self.icon_buffer = draw_thumbnail(shm, &icon, image)
+            .inspect_err(|e| warn!("创建拖动图标失败: {}", e))
+            .ok();
+
+        data_device.start_drag(Some(&source), origin, Some(&icon), serial);
+        icon.commit();
+
+        self.source = Some(source);
+        self.icon = Some(icon);
+        Ok(())
+    }
+
+    /// 拖放目标请求数据时写入 fd
+    ///
+    /// 目标读取很慢时写入会阻塞，放到单独的线程中，不挡住事件循环
+    pub fn send(&self, mime_type: &str, fd: OwnedFd) {
+        let data = match mime_type {
+            MIME_PNG => Arc::clone(&self.png),
+            MIME_URI_LIST => match self.uri_list.as_ref() {
+                Some(uri_list) => Arc::new(uri_list.as_bytes().to_vec()),
+                None => return,
+            },
+            _ => return,
+        };
+        thread::spawn(move || {
+            let mut file = std::fs::File::from(fd);
+            if let Err(e) = file.write_all(&data) {
+                warn!("发送拖放数据失败: {}", e);
+            }
+        });
+    }
+
+    /// 拖放结束或取消时释放资源
+    pub fn finish(&mut self) {
+        if let Some(source) = self.source.take() {
+            source.destroy();
+        }
+        if let Some(icon) = self.icon.take() {
+            icon.destroy();
+        }
+        self.icon_buffer = None;
+        self.png = Arc::default();
+        self.uri_list = None;
+    }
+}
+
+/// 把截图缩小为拖动图标并附加到 surface
+fn draw_thumbnail(
+    shm: &Shm,
+    surface: &wl_surface::WlSurface,
+    image: &ImageSurface,
+) -> Result<(SlotPool, Buffer), Box<dyn Error>> {
+    let scale = (THUMBNAIL_SIZE / image.width().max(image.height()) as f64).min(1.0);
+    let width = ((image.width() as f64 * scale).round() as i32).max(1);
+    let height = ((image.height() as f64 * scale).round() as i32).max(1);
+
+    let mut pool = SlotPool::new((width * height * 4) as usize, shm)?;
+    let (buffer, canvas) = pool.create_buffer(width, height, width * 4, Format::Argb8888)?;
+    canvas.fill(0);
+
+    let cairo_surface = unsafe {
+        ImageSurface::create_for_data_unsafe(
+            canvas.as_mut_ptr(),
+            CairoFormat::ARgb32,
+            width,
+            height,
+            width * 4,
+        )?
+    };
+    {
+        let cr = cairo::Context::new(&cairo_surface)?;
+        cr.scale(scale, scale);
+        cr.set_source_surface(image, 0.0, 0.0)?;
+        cr.paint_with_alpha(0.8)?;
+    }
+    drop(cairo_surface);
+
+    buffer.attach_to(surface)?;
+    surface.damage_buffer(0, 0, width, height);
+    Ok((pool, buffer))
+}
+
+/// 绝对路径转换为 file:// URI，非 URI 安全的字节进行百分号编码
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
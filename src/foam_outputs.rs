@@ -11,7 +11,37 @@ use wayland_protocols_wlr::layer_shell::v1::client::{
     zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity},
 };
 
-use crate::{cairo_render::draw_base, foamshot::FoamShot, select_rect::SubRect};
+use crate::{
+    action_bar::ActionBar,
+    annotation::Annotator,
+    cairo_render::draw_base,
+    context_menu::ContextMenu,
+    foamshot::FoamShot,
+    measure::MeasureState,
+    select_rect::{SelectRect, SubRect},
+    toolbar::Toolbar,
+};
+
+/// 绘制在遮罩层之上的内容，坐标均为全局坐标
+#[derive(Default, Clone, Copy)]
+pub struct Overlay<'a> {
+    pub menu: Option<&'a ContextMenu>,
+    pub measure: Option<&'a MeasureState>,
+    pub annotator: Option<&'a Annotator>,
+    pub toolbar: Option<&'a Toolbar>,
+    pub action_bar: Option<&'a ActionBar>,
+}
+
+/// 冻结画面的放大状态，surface 上的 (x, y) 对应输出上的 (offset + (x, y) / factor)
+#[derive(Debug, Clone, Copy)]
+pub struct Zoom {
+    pub factor: f64,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+const ZOOM_STEP: f64 = 1.25;
+const MAX_ZOOM: f64 = 8.0;
 
 /// NOTE: 为物理显示器做的抽象，包含其基础信息
 #[derive(Default, Debug)]
@@ -41,8 +71,14 @@ pub struct FoamOutput {
     pub layer_surface: Option<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1>,
     // TODO: add sub rect with Option
     pub subrect: Option<SubRect>,
+    /// 已提交选区在该输出上的子矩形
+    pub committed_subrects: Vec<SubRect>,
 
     pub need_redraw: bool,
+    /// 拖放时附加了空 buffer 而隐藏，取消拖放后在下一次 configure 时重新附加
+    pub hidden: bool,
+    /// 冻结画面放大时的状态，None 表示未放大
+    pub zoom: Option<Zoom>,
 
     pub pool: Option<slot::SlotPool>,
 }
@@ -73,6 +109,95 @@ impl FoamOutput {
 
         (dst_x, dst_y)
     }
+    /// 逻辑尺寸，xdg_output 未提供时按缩放从物理尺寸推算
+    pub fn logical_size(&self) -> (i32, i32) {
+        if self.logical_width > 0 && self.logical_height > 0 {
+            (self.logical_width, self.logical_height)
+        } else {
+            (
+                (self.width as f64 / self.scale).round() as i32,
+                (self.height as f64 / self.scale).round() as i32,
+            )
+        }
+    }
+
+    /// 逻辑坐标到物理像素的换算系数
+    pub fn physical_factor(&self) -> (f64, f64) {
+        let (lw, lh) = self.logical_size();
+        if lw > 0 && lh > 0 {
+            (
+                self.width as f64 / lw as f64,
+                self.height as f64 / lh as f64,
+            )
+        } else {
+            (self.scale, self.scale)
+        }
+    }
+
+    /// 将 surface 上的坐标换算为未放大时输出上的坐标
+    pub fn unzoom_pos(&self, x: f64, y: f64) -> (f64, f64) {
+        match self.zoom {
+            Some(zoom) => (
+                zoom.offset_x + x / zoom.factor,
+                zoom.offset_y + y / zoom.factor,
+            ),
+            None => (x, y),
+        }
+    }
+
+    /// 以输出上的 (x, y) 为中心放大或缩小 steps 级，该点在屏幕上的位置保持不变
+    pub fn zoom_at(&mut self, x: f64, y: f64, steps: i32) {
+        let (factor, offset_x, offset_y) = self
+            .zoom
+            .map_or((1.0, 0.0, 0.0), |z| (z.factor, z.offset_x, z.offset_y));
+        let new_factor = (factor * ZOOM_STEP.powi(steps)).clamp(1.0, MAX_ZOOM);
+        if new_factor <= 1.0 {
+            self.zoom = None;
+            return;
+        }
+
+        // 该点当前在 surface 上的位置
+        let (sx, sy) = ((x - offset_x) * factor, (y - offset_y) * factor);
+        let (lw, lh) = self.logical_size();
+        let (lw, lh) = (lw as f64, lh as f64);
+        self.zoom = Some(Zoom {
+            factor: new_factor,
+            offset_x: (x - sx / new_factor).clamp(0.0, lw - lw / new_factor),
+            offset_y: (y - sy / new_factor).clamp(0.0, lh - lh / new_factor),
+        });
+    }
+
+    /// 计算全局选区与该输出的交集，返回相对于输出的子矩形
+    pub fn intersect(&self, rect: &SelectRect) -> Option<SubRect> {
+        let intersect_left = self.global_x.max(rect.sx);
+        let intersect_top = self.global_y.max(rect.sy);
+        let intersect_right = (self.global_x + self.width).min(rect.ex);
+        let intersect_bottom = (self.global_y + self.height).min(rect.ey);
+
+        // 判断有效交集区域
+        if intersect_left < intersect_right && intersect_top < intersect_bottom {
+            // 计算相对于输出的局部坐标
+            Some(SubRect::new(
+                self.id,
+                intersect_left - self.global_x,
+                intersect_top - self.global_y,
+                intersect_right - intersect_left,
+                intersect_bottom - intersect_top,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// 请求帧回调，在 wl_callback 中重绘
+    pub fn request_frame(&mut self, qh: &QueueHandle<FoamShot>) {
+        if let Some(surface) = &mut self.surface {
+            surface.frame(qh, self.id);
+            surface.set_buffer_scale(self.scale.round() as i32);
+            surface.commit();
+        }
+    }
+
     pub fn new_subrect(&mut self, x: i32, y: i32, w: i32, h: i32) {
         if w <= 0 || h <= 0 {
             self.subrect = None
@@ -164,17 +289,12 @@ impl FoamOutput {
         self.base_buffer = Some(buffer)
     }
 
-    pub fn update_select_subrect(&mut self, base_canvas: &[u8], freeze: bool) {
-        if self.subrect.is_none() {
-            return;
-        }
-
+    pub fn update_select_subrect(&mut self, base_canvas: &[u8], freeze: bool, overlay: Overlay) {
         let (w, h) = (self.width, self.height);
-        let surface = self.surface.as_ref().expect("Missing surfaces");
         let pool = self.pool.as_mut().unwrap();
         let (buffer, canvas) = pool.create_buffer(w, h, w * 4, Format::Argb8888).unwrap();
 
-        if freeze {
+        if freeze && self.zoom.is_none() {
             canvas.copy_from_slice(base_canvas);
         } else {
             canvas.fill(0);
@@ -194,16 +314,102 @@ impl FoamOutput {
         let cr = Context::new(&cairo_surface).unwrap();
         cr.scale(self.scale, self.scale);
 
+        let zoom_factor = self.zoom.map_or(1.0, |z| z.factor);
+        if let Some(zoom) = self.zoom {
+            cr.scale(zoom.factor, zoom.factor);
+            cr.translate(-zoom.offset_x, -zoom.offset_y);
+
+            // 放大时重新绘制冻结画面，使用最近邻采样以便看清每个像素
+            if freeze {
+                canvas_fill_zoomed(&cr, base_canvas, w, h, self.scale);
+            }
+        }
+
         // 获取Cairo表面尺寸
         let surface_width = cairo_surface.width() as f64;
         let surface_height = cairo_surface.height() as f64;
 
+        let rects: Vec<&SubRect> = self
+            .committed_subrects
+            .iter()
+            .chain(self.subrect.as_ref())
+            .collect();
+
+        // 测量模式下不绘制遮罩和选区，只绘制测量结果
+        if let Some(measure) = overlay.measure {
+            measure.draw(&cr, (self.global_x, self.global_y));
+            return self.commit_canvas(buffer);
+        }
+
+        // 在独立的 group 中绘制半透明遮罩，再清除所有选区，选区重叠时也不会反色
+        cr.push_group();
         // 设置半透明白色
         cr.set_source_rgba(0.8, 0.8, 0.8, 0.3);
         cr.rectangle(0.0, 0.0, surface_width, surface_height);
+        cr.fill().unwrap();
+
+        cr.set_operator(cairo::Operator::Clear);
+        for subrect in &rects {
+            cr.rectangle(
+                subrect.relative_min_x.into(),
+                subrect.relative_min_y.into(),
+                subrect.width.into(),
+                subrect.height.into(),
+            );
+        }
+        cr.fill().unwrap();
+        cr.pop_group_to_source().unwrap();
+        cr.paint().unwrap();
+
+        // 添加边框（根据与显示器边缘的重合情况决定是否绘制）
+        cr.save().unwrap(); // 保存当前状态
+        cr.set_line_width(2.0 / zoom_factor); // 设置边框宽度，放大时保持细线
+        cr.set_source_rgba(0.0, 0.0, 0.0, 1.0); // 设置边框颜色为黑色
+        for subrect in &rects {
+            self.draw_border(&cr, subrect);
+        }
+        cr.stroke().unwrap(); // 绘制边框
+        cr.restore().unwrap(); // 恢复状态
+
+        let origin = (self.global_x, self.global_y);
+        if let Some(annotator) = overlay.annotator {
+            annotator.draw(&cr, (origin.0 as f64, origin.1 as f64));
+            if !freeze {
+                annotator.draw_redaction_placeholders(&cr, (origin.0 as f64, origin.1 as f64));
+            }
+            annotator.draw_selection(&cr, (origin.0 as f64, origin.1 as f64));
+        }
+        if let (Some(toolbar), Some(annotator)) = (overlay.toolbar, overlay.annotator) {
+            toolbar.draw(&cr, origin, annotator);
+        }
+        if let Some(action_bar) = overlay.action_bar {
+            action_bar.draw(&cr, origin);
+        }
+        if let Some(menu) = overlay.menu {
+            menu.draw(&cr, origin);
+        }
+
+        self.commit_canvas(buffer);
+    }
+
+    /// 提交绘制完成的 buffer
+    fn commit_canvas(&mut self, buffer: Buffer) {
+        let (w, h) = (self.width, self.height);
+        let surface = self.surface.as_ref().expect("Missing surfaces");
+
+        buffer.attach_to(surface).unwrap(); // 如果 attach_to 失败则返回
+
+        surface.damage_buffer(0, 0, w, h);
 
-        let subrect = self.subrect.as_ref().unwrap();
+        // 提交 surface
+        surface.set_buffer_scale(self.scale.round() as i32);
+        surface.commit();
+        self.need_redraw = false;
+        self.base_buffer = Some(buffer)
+    }
 
+    /// 为子矩形添加边框路径，与显示器边缘重合的边不绘制
+    fn draw_border(&self, cr: &Context, subrect: &SubRect) {
         let (x, y, rw, rh) = (
             subrect.relative_min_x,
             subrect.relative_min_y,
@@ -211,20 +417,6 @@ impl FoamOutput {
             subrect.height,
         );
 
-        // 添加内部矩形路径（作为裁剪区域）
-        cr.rectangle(x.into(), y.into(), rw.into(), rh.into());
-
-        // 使用奇偶填充规则，形成环形区域
-        cr.set_fill_rule(cairo::FillRule::EvenOdd);
-
-        // 填充路径区域
-        cr.fill().unwrap();
-
-        // 添加边框（根据与显示器边缘的重合情况决定是否绘制）
-        cr.save().unwrap(); // 保存当前状态
-        cr.set_line_width(2.0); // 设置边框宽度
-        cr.set_source_rgba(0.0, 0.0, 0.0, 1.0); // 设置边框颜色为黑色
-
         // 判断是否绘制左边
         if subrect.relative_min_x > 0 {
             cr.move_to(x.into(), y.into());
@@ -248,20 +440,20 @@ impl FoamOutput {
             cr.move_to(x.into(), (y + rh).into());
             cr.line_to((x + rw).into(), (y + rh).into());
         }
-
-        cr.stroke().unwrap(); // 绘制边框
-        cr.restore().unwrap(); // 恢复状态
-
-        // surface.frame(qh, self.id);
-
-        buffer.attach_to(surface).unwrap(); // 如果 attach_to 失败则返回
-
-        surface.damage_buffer(0, 0, w, h);
-
-        // 提交 surface
-        surface.set_buffer_scale(self.scale.round() as i32);
-        surface.commit();
-        self.need_redraw = false;
-        self.base_buffer = Some(buffer)
     }
 }
+
+/// 在已设置放大变换的 Context 上绘制冻结画面
+fn canvas_fill_zoomed(cr: &Context, base_canvas: &[u8], w: i32, h: i32, scale: f64) {
+    let Ok(base) =
+        ImageSurface::create_for_data(base_canvas.to_vec(), cairo::Format::ARgb32, w, h, w * 4)
+    else {
+        return;
+    };
+    cr.save().unwrap();
+    cr.scale(1.0 / scale, 1.0 / scale);
+    cr.set_source_surface(&base, 0.0, 0.0).unwrap();
+    cr.source().set_filter(cairo::Filter::Nearest);
+    cr.paint().unwrap();
+    cr.restore().unwrap();
+}
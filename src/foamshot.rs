@@ -1,11 +1,19 @@
+use std::time::Instant;
+
 use log::{debug, error};
 use smithay_client_toolkit::shm::Shm;
 use wayland_client::{Connection, EventQueue, globals::registry_queue_init};
 
 use crate::{
-    action::{self, Action, IsFreeze},
-    config::{FoamConfig, ImageType},
+    action::{self, Action, EditAction, ExitAction, IsFreeze, MeasureAction, PickAction},
+    color_picker::{self, PickResult},
+    config::{FoamConfig, ImageType, Mode},
+    context_menu::MenuItem,
+    history,
+    hook::{self, Hook},
     notify::{self, NotificationLevel},
+    project::Project,
+    result::{CaptureResult, ColorResult, Status, Stopwatch},
     save_helper, wayland_ctx,
 };
 
@@ -14,6 +22,13 @@ pub struct FoamShot {
     pub wayland_ctx: wayland_ctx::WaylandCtx,
 
     pub action: action::Action,
+
+    /// 启动和保存的计时，用于 `--json` 的结果
+    pub stopwatch: Stopwatch,
+    /// 直接复制到剪贴板的 PNG 字节数
+    pub copied_bytes: Option<u64>,
+    /// 取色模式选中的颜色
+    pub picked_color: Option<PickResult>,
 }
 
 /// run
@@ -32,6 +47,7 @@ pub fn run_main_loop() {
     event_queue.roundtrip(&mut shot_foam).expect("init failed");
 
     shot_foam.wait_freeze(&mut event_queue);
+    shot_foam.load_project();
 
     // NOTE: 创建layer && surface提交
     shot_foam.wayland_ctx.init_base_layers();
@@ -64,36 +80,117 @@ pub fn run_main_loop() {
                         shot_foam.toggle_freeze(&mut event_queue);
                     }
                 }
-                shot_foam.action = Action::WaitPointerPress
+                shot_foam.action = shot_foam.idle_action()
             }
             Action::OnDraw => {
                 // shot_foam.wayland_ctx.update_select_region();
             }
             Action::OnEdit(_a) => {}
-            Action::Exit => {
+            Action::Pick(PickAction::Done) => {
+                shot_foam.pick_color(&mut event_queue);
+            }
+            Action::Pick(_) => {}
+            Action::Measure(_) => {}
+            Action::Exit(ExitAction::Copy) => {
+                if !shot_foam.wayland_ctx.current_freeze {
+                    shot_foam.wait_freeze(&mut event_queue);
+                }
+                let started = Instant::now();
+                let copied = save_helper::copy_png_to_wl_clipboard(&mut shot_foam.wayland_ctx);
+                shot_foam.stopwatch.save = Some(started.elapsed());
+                shot_foam.wayland_ctx.remember_global_rect();
+                match copied {
+                    Ok(bytes) => {
+                        shot_foam.copied_bytes = Some(bytes as u64);
+                        shot_foam.finish(Status::Copied, None)
+                    }
+                    Err(e) => {
+                        shot_foam.send_error("copy to clipboard error");
+                        log::error!("copy to clipboard error: {}", e);
+                        shot_foam.finish(Status::Error, Some(e.to_string()))
+                    }
+                }
+            }
+            Action::Pin => {}
+            Action::Exit(ExitAction::Pin) => shot_foam.pin_capture(&mut event_queue),
+            // --pin 时保存操作改为钉图
+            Action::Exit(ExitAction::Save) if shot_foam.wayland_ctx.config.pin => {
+                shot_foam.pin_capture(&mut event_queue)
+            }
+            Action::Exit(exit @ (ExitAction::Save | ExitAction::Hook)) => {
+                let exit = *exit;
                 shot_foam.wayland_ctx.config = FoamConfig::new();
+                if let Some(path) = shot_foam.wayland_ctx.save_as.take() {
+                    shot_foam.wayland_ctx.config.set_output_path(path);
+                }
                 if !shot_foam.wayland_ctx.current_freeze {
                     shot_foam.wait_freeze(&mut event_queue);
                 }
+                let started = Instant::now();
+                let mut save_error = None;
                 match shot_foam.wayland_ctx.config.image_type {
                     ImageType::Png => {
                         if let Err(e) = save_helper::save_to_png(&mut shot_foam.wayland_ctx) {
                             shot_foam.send_error("image saved error");
                             log::error!("save to png error: {}", e);
+                            save_error = Some(e.to_string());
+                        }
+                        if let Err(e) =
+                            save_helper::save_to_wl_clipboard(&mut shot_foam.wayland_ctx)
+                        {
+                            log::error!("copy to clipboard error: {}", e);
                         }
-                        save_helper::save_to_wl_clipboard(&mut shot_foam.wayland_ctx).unwrap();
                     }
                     ImageType::Jpg => {
                         if let Err(e) = save_helper::save_to_jpg(&mut shot_foam.wayland_ctx, 100) {
                             shot_foam.send_error("image saved error");
                             log::error!("save to jpg error: {}", e);
+                            save_error = Some(e.to_string());
+                        }
+                        if let Err(e) =
+                            save_helper::save_to_wl_clipboard(&mut shot_foam.wayland_ctx)
+                        {
+                            log::error!("copy to clipboard error: {}", e);
+                        }
+                    }
+                    image_type @ (ImageType::Svg | ImageType::Pdf) => {
+                        if let Err(e) =
+                            save_helper::save_to_vector(&mut shot_foam.wayland_ctx, image_type)
+                        {
+                            shot_foam.send_error("image saved error");
+                            log::error!("save to {:?} error: {}", image_type, e);
+                            save_error = Some(e.to_string());
+                        }
+                        // 剪贴板中的图片需要是位图
+                        if shot_foam.wayland_ctx.config.auto_copy
+                            && let Err(e) =
+                                save_helper::copy_png_to_wl_clipboard(&mut shot_foam.wayland_ctx)
+                        {
+                            log::error!("copy to clipboard error: {}", e);
                         }
-                        save_helper::save_to_wl_clipboard(&mut shot_foam.wayland_ctx).ok();
                     }
                 }
-                shot_foam.send_save_info();
+                shot_foam.stopwatch.save = Some(started.elapsed());
+                // 没有有效选区时保存函数不写入文件，不能当作保存成功
+                if save_error.is_none() && shot_foam.wayland_ctx.saved_images.is_empty() {
+                    shot_foam.send_error("no region to save");
+                    save_error = Some("no region to save".to_string());
+                }
+                shot_foam.save_project();
+                shot_foam.wayland_ctx.remember_global_rect();
+
+                // 保存通知的按钮在遮罩层关闭后处理
+                shot_foam.hide_overlays(&mut event_queue);
+                let hooks = shot_foam.selected_hooks(exit == ExitAction::Hook);
+                if hooks.is_empty() || !shot_foam.wayland_ctx.config.wait_hooks {
+                    shot_foam.send_save_info(None);
+                }
+                shot_foam.run_hooks(&mut event_queue, hooks);
 
-                std::process::exit(0)
+                match save_error {
+                    Some(e) => shot_foam.finish(Status::Error, Some(e)),
+                    None => shot_foam.finish(Status::Saved, None),
+                }
             }
         }
     }
@@ -105,6 +202,9 @@ impl FoamShot {
         Self {
             wayland_ctx: wayland_ctx::WaylandCtx::new(shm, qh),
             action: Action::default(),
+            stopwatch: Stopwatch::default(),
+            copied_bytes: None,
+            picked_color: None,
         }
     }
 
@@ -132,7 +232,7 @@ impl FoamShot {
                 Err(e) => {
                     error!("error in wait_freeze: {}", e);
                     self.send_error("error about wait screencopy");
-                    std::process::exit(0)
+                    self.finish(Status::Error, Some(e.to_string()))
                 }
             }
             // event_queue.blocking_dispatch(self).unwrap();
@@ -159,29 +259,258 @@ impl FoamShot {
         event_queue.blocking_dispatch(self).unwrap();
     }
 
+    /// 打开要重新编辑的项目文件，用其中的截图替换刚刚冻结的画面
+    fn load_project(&mut self) {
+        let Some(path) = self.wayland_ctx.config.project_input.clone() else {
+            return;
+        };
+        if let Err(e) = Project::read(&path).and_then(|p| p.apply(&mut self.wayland_ctx)) {
+            error!("load project error: {}", e);
+            self.send_error("can not open the project file");
+            self.finish(Status::Error, Some(e.to_string()))
+        }
+    }
+
+    /// 按配置将原始截图、选区和标注保存为项目文件
+    fn save_project(&mut self) {
+        let Some(path) = self.wayland_ctx.config.project_output.clone() else {
+            return;
+        };
+        if let Err(e) = Project::capture(&self.wayland_ctx).and_then(|p| p.write(&path)) {
+            error!("save project error: {}", e);
+            self.send_error("project saved error");
+        }
+    }
+
+    /// 将截图钉在屏幕上，之后只处理钉图窗口的事件，关闭窗口时退出
+    fn pin_capture(&mut self, event_queue: &mut EventQueue<FoamShot>) {
+        if !self.wayland_ctx.current_freeze {
+            self.wait_freeze(event_queue);
+        }
+        if let Err(e) = self.wayland_ctx.start_pin() {
+            error!("pin error: {}", e);
+            self.send_error("can not pin the capture");
+            self.finish(Status::Error, Some(e.to_string()))
+        }
+        self.wayland_ctx.remember_global_rect();
+        self.action = Action::Pin;
+    }
+
+    /// 恢复上一次保存的选区，布局变化时给出警告
+    pub fn restore_last_region(&mut self) -> bool {
+        match self.wayland_ctx.restore_last_rect() {
+            None => {
+                self.send_warn("no saved region from the last capture");
+                false
+            }
+            Some(layout_matches) => {
+                if !layout_matches {
+                    self.send_warn("output layout changed since the last region was saved");
+                }
+                true
+            }
+        }
+    }
+
+    /// 执行右键菜单中的操作
+    pub fn run_menu_item(&mut self, item: MenuItem) {
+        match item {
+            MenuItem::Save => self.action = Action::Exit(ExitAction::Save),
+            MenuItem::SaveAs => {
+                self.wayland_ctx.begin_rename();
+                self.action = Action::OnEdit(EditAction::Rename);
+            }
+            MenuItem::Hook => self.action = Action::Exit(ExitAction::Hook),
+            MenuItem::Copy => self.action = Action::Exit(ExitAction::Copy),
+            MenuItem::Pin => self.action = Action::Exit(ExitAction::Pin),
+            MenuItem::Discard => self.finish(Status::Cancelled, None),
+        }
+    }
+
+    /// 当前模式下等待鼠标按下的状态
+    pub fn idle_action(&self) -> Action {
+        match self.wayland_ctx.config.mode {
+            Mode::PickColor => Action::Pick(PickAction::WaitPress),
+            Mode::Measure => Action::Measure(MeasureAction::WaitPress),
+            Mode::Shot => Action::WaitPointerPress,
+        }
+    }
+
+    /// 从冻结的画面中读取颜色，输出到 stdout 并复制选定的格式
+    pub fn pick_color(&mut self, event_queue: &mut EventQueue<FoamShot>) -> ! {
+        if !self.wayland_ctx.current_freeze {
+            self.wait_freeze(event_queue);
+        }
+
+        let Some(result) = color_picker::pick(&self.wayland_ctx) else {
+            self.send_warn("no color under the selection");
+            self.finish(
+                Status::Error,
+                Some("no color under the selection".to_string()),
+            );
+        };
+        self.picked_color = Some(result);
+
+        let color = result.color;
+        // --json 时颜色在退出时的 JSON 中输出
+        if !self.wayland_ctx.config.json {
+            if result.samples > 1 {
+                println!("average of {} px", result.samples);
+            }
+            println!("{}", color.to_hex());
+            println!("{}", color.to_rgb());
+            println!("{}", color.to_hsl());
+        }
+
+        let text = color.format(self.wayland_ctx.config.color_format);
+        if self.wayland_ctx.config.auto_copy
+            && let Err(e) = save_helper::copy_text_to_wl_clipboard(&text)
+        {
+            error!("copy color error: {}", e);
+            self.send_error("can not copy color to clipboard");
+        }
+        notify::send(
+            NotificationLevel::Info,
+            "color picked",
+            text,
+            "color-select",
+            self.wayland_ctx.config.allow_notify,
+        );
+        self.finish(Status::Picked, None)
+    }
+
     /// if current compositor unsupported zwl screencopy, foamshot will be exit
     pub fn check_ok(&self) {
         // check screencopy manager exists
         if self.wayland_ctx.scm.manager.is_none() {
             self.send_error("this compositor unsupported zwl screencopy, foamshot will be exit");
-            std::process::exit(1);
+            self.finish(
+                Status::Error,
+                Some("zwlr_screencopy_manager_v1 is not supported".to_string()),
+            );
         }
     }
 
-    pub fn send_save_info(&self) {
-        notify::send(
-            NotificationLevel::Info,
-            "image_saved",
-            format!(
-                "Image saved in {}",
-                self.wayland_ctx.config.output_path.clone().display()
-            ),
-            self.wayland_ctx
-                .config
-                .output_path
-                .to_str()
-                .unwrap()
-                .to_string(),
+    /// 保存后要运行的钩子，all_named 时没有指定钩子则运行配置文件中的所有命名钩子
+    fn selected_hooks(&self, all_named: bool) -> Vec<Hook> {
+        let config = &self.wayland_ctx.config;
+        let hooks = Hook::selected(&config.exec, &config.hooks).and_then(|hooks| {
+            if hooks.is_empty() && all_named {
+                Hook::load_named()
+            } else {
+                Ok(hooks)
+            }
+        });
+        match hooks {
+            Ok(hooks) => {
+                if hooks.is_empty() && all_named {
+                    self.send_warn("no hook configured");
+                }
+                hooks
+            }
+            Err(e) => {
+                error!("load hooks error: {}", e);
+                self.send_error(&e.to_string());
+                Vec::new()
+            }
+        }
+    }
+
+    /// 销毁遮罩层并立即发送请求，之后的等待不会挡住屏幕
+    fn hide_overlays(&mut self, event_queue: &mut EventQueue<FoamShot>) {
+        self.wayland_ctx.destroy_overlays();
+        if let Err(e) = event_queue.flush() {
+            error!("flush error: {}", e);
+        }
+    }
+
+    /// 对每个保存的文件运行钩子，先隐藏遮罩层
+    ///
+    /// `--wait-hooks` 时最多等待 `--hook-timeout`，失败或超时发送错误通知，否则钩子在后台运行
+    fn run_hooks(&mut self, event_queue: &mut EventQueue<FoamShot>, hooks: Vec<Hook>) {
+        if hooks.is_empty() {
+            return;
+        }
+        self.hide_overlays(event_queue);
+
+        let wait = self.wayland_ctx.config.wait_hooks;
+        let outputs = self.wayland_ctx.foam_outputs.as_deref().unwrap_or_default();
+        let mut children = Vec::new();
+        for image in &self.wayland_ctx.saved_images {
+            for hook in &hooks {
+                debug!("run hook {}: {}", hook.name, hook.command);
+                match hook.spawn(image, outputs, !wait) {
+                    Ok(child) => children.push((hook.name.clone(), child)),
+                    Err(e) => {
+                        error!("spawn hook {} error: {}", hook.name, e);
+                        self.send_error(&format!("can not run hook {}: {}", hook.name, e));
+                    }
+                }
+            }
+        }
+        if !wait {
+            return;
+        }
+
+        let deadline = Instant::now() + self.wayland_ctx.config.hook_timeout;
+        let mut stdout = Vec::new();
+        for (name, child) in children {
+            let output = hook::wait(&name, child, deadline);
+            if !output.success {
+                let status = match output.code {
+                    _ if output.timed_out => "a timeout".to_string(),
+                    Some(code) => format!("code {}", code),
+                    None => "a signal".to_string(),
+                };
+                error!("hook {} exited with {}: {}", name, status, output.stderr);
+                self.send_error(&format!(
+                    "hook {} exited with {}\n{}",
+                    output.name, status, output.stderr
+                ));
+            }
+            if !output.stdout.is_empty() {
+                stdout.push(output.stdout);
+            }
+        }
+
+        self.send_save_info(Some(&stdout.join("\n")));
+    }
+
+    /// 所有退出路径的出口，保存过文件时记录历史，`--json` 时先在 stdout 输出结果，出错时退出码为 1，取消时为 2
+    pub fn finish(&self, status: Status, error: Option<String>) -> ! {
+        let outputs = self.wayland_ctx.foam_outputs.as_deref().unwrap_or_default();
+        if let Err(e) = history::append(&self.wayland_ctx.saved_images, outputs) {
+            error!("append history error: {}", e);
+        }
+        if self.wayland_ctx.config.json {
+            let mut result = CaptureResult::collect(
+                &self.wayland_ctx,
+                status,
+                error,
+                self.copied_bytes,
+                &self.stopwatch,
+            );
+            result.color = self.picked_color.map(ColorResult::from);
+            result.print();
+        }
+        std::process::exit(status.exit_code())
+    }
+
+    pub fn send_save_info(&mut self, hook_output: Option<&str>) {
+        let images = &self.wayland_ctx.saved_images;
+        let body = images
+            .iter()
+            .map(|image| image.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let body = match hook_output.filter(|output| !output.is_empty()) {
+            Some(output) => format!("Image saved in {}\n{}", body, output),
+            None => format!("Image saved in {}", body),
+        };
+        notify::spawn_saved(
+            &body,
+            images,
+            self.wayland_ctx.config.notify_timeout,
             self.wayland_ctx.config.allow_notify,
         );
     }
@@ -0,0 +1,337 @@
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{DateTime, Duration, Local};
+use clap::ValueEnum;
+use directories::ProjectDirs;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{FoamConfig, HistoryCommand, ImageType};
+use crate::foam_outputs::FoamOutput;
+use crate::save_helper::{self, SavedImage};
+
+/// 配置文件中的保留策略，如 `{"history": {"max_age_days": 90, "max_entries": 1000}}`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct HistoryConfig {
+    /// 为 false 时不记录历史
+    enabled: bool,
+    max_age_days: Option<u32>,
+    max_entries: Option<usize>,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_age_days: None,
+            max_entries: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    history: HistoryConfig,
+}
+
+impl HistoryConfig {
+    /// 配置文件不存在或无法解析时使用默认值
+    fn load() -> Self {
+        FoamConfig::config_file_path()
+            .and_then(|path| fs::read(path).ok())
+            .and_then(|data| {
+                serde_json::from_slice::<ConfigFile>(&data)
+                    .inspect_err(|e| warn!("解析配置文件失败: {}", e))
+                    .ok()
+            })
+            .unwrap_or_default()
+            .history
+    }
+}
+
+/// 历史记录中的区域，全局逻辑坐标
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct HistoryRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// NOTE: `$XDG_DATA_HOME/foamshot/history.jsonl` 中的一行，每次保存的每个文件一条
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    /// RFC 3339 格式的保存时间
+    pub time: String,
+    pub region: HistoryRegion,
+    pub outputs: Vec<String>,
+    pub format: String,
+}
+
+impl HistoryEntry {
+    fn new(image: &SavedImage, outputs: &[FoamOutput]) -> Self {
+        let (x, y, width, height) = image.region;
+        let mut ids: Vec<usize> = image.subrects.iter().map(|r| r.monitor_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        Self {
+            // 相对路径在其他目录下运行 history 时会失效
+            path: image.path.canonicalize().unwrap_or(image.path.clone()),
+            time: image.time.to_rfc3339(),
+            region: HistoryRegion {
+                x,
+                y,
+                width,
+                height,
+            },
+            outputs: ids
+                .iter()
+                .filter_map(|&id| outputs.get(id))
+                .map(|o| o.name.clone())
+                .collect(),
+            format: image.format.extension().to_string(),
+        }
+    }
+
+    fn time(&self) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(&self.time)
+            .ok()
+            .map(|t| t.with_timezone(&Local))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    fn mime_type(&self) -> &'static str {
+        ImageType::from_str(&self.format, true).map_or("image/png", ImageType::mime_type)
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("", "", env!("CARGO_PKG_NAME"))?;
+    Some(dirs.data_dir().join("history.jsonl"))
+}
+
+/// 历史记录文件的内容
+#[derive(Debug, Default)]
+struct History {
+    /// 按保存顺序排列的记录
+    entries: Vec<HistoryEntry>,
+    /// 无法解析的行，例如较新版本写入的记录，重写文件时原样保留
+    unparsed: Vec<String>,
+}
+
+impl History {
+    fn load() -> Result<Self, Box<dyn Error>> {
+        let Some(path) = history_path().filter(|p| p.exists()) else {
+            return Ok(Self::default());
+        };
+        let data = fs::read_to_string(&path).map_err(|e| format!("读取历史记录失败: {}", e))?;
+        Ok(Self::parse(&data))
+    }
+
+    fn parse(data: &str) -> Self {
+        let mut history = Self::default();
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(entry) => history.entries.push(entry),
+                Err(e) => {
+                    warn!("跳过无法解析的历史记录: {}", e);
+                    history.unparsed.push(line.to_string());
+                }
+            }
+        }
+        history
+    }
+
+    /// 无法解析的行写在最前面
+    fn to_jsonl(&self) -> Result<String, Box<dyn Error>> {
+        let mut data = String::new();
+        for line in &self.unparsed {
+            data.push_str(line);
+            data.push('\n');
+        }
+        for entry in &self.entries {
+            data.push_str(&serde_json::to_string(entry)?);
+            data.push('\n');
+        }
+        Ok(data)
+    }
+
+    fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = history_path().ok_or("无法确定数据目录")?;
+        fs::write(path, self.to_jsonl()?).map_err(|e| format!("写入历史记录失败: {}", e))?;
+        Ok(())
+    }
+}
+
+/// 追加本次保存的文件，配置了保留策略时顺便清理旧记录
+pub fn append(images: &[SavedImage], outputs: &[FoamOutput]) -> Result<(), Box<dyn Error>> {
+    let config = HistoryConfig::load();
+    if !config.enabled || images.is_empty() {
+        return Ok(());
+    }
+
+    let path = history_path().ok_or("无法确定数据目录")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("打开历史记录失败: {}", e))?;
+    for image in images {
+        let line = serde_json::to_string(&HistoryEntry::new(image, outputs))?;
+        writeln!(file, "{}", line)?;
+    }
+
+    if config.max_age_days.is_some() || config.max_entries.is_some() {
+        prune(config.max_age_days, config.max_entries, false)?;
+    }
+    Ok(())
+}
+
+/// 删除超过 max_age_days 天或超出最近 max_entries 条的记录，返回删除的记录
+fn prune(
+    max_age_days: Option<u32>,
+    max_entries: Option<usize>,
+    delete_files: bool,
+) -> Result<Vec<HistoryEntry>, Box<dyn Error>> {
+    let mut history = History::load()?;
+    let entries = std::mem::take(&mut history.entries);
+    let cutoff = max_age_days.map(|days| Local::now() - Duration::days(days as i64));
+    let keep_from = max_entries.map_or(0, |max| entries.len().saturating_sub(max));
+
+    let (kept, removed): (Vec<_>, Vec<_>) =
+        entries.into_iter().enumerate().partition(|(i, entry)| {
+            // 时间无法解析的记录只按数量清理
+            let too_old = cutoff.is_some_and(|cutoff| entry.time().is_some_and(|t| t < cutoff));
+            *i >= keep_from && !too_old
+        });
+    if removed.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    history.entries = kept.into_iter().map(|(_, entry)| entry).collect();
+    let removed: Vec<HistoryEntry> = removed.into_iter().map(|(_, entry)| entry).collect();
+    history.save()?;
+    if delete_files {
+        for entry in removed.iter().filter(|e| e.exists()) {
+            if let Err(e) = fs::remove_file(&entry.path) {
+                warn!("删除 {} 失败: {}", entry.path.display(), e);
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// 编号从最新的记录开始为 1，省略时为最新的记录
+fn find(entries: &[HistoryEntry], id: Option<usize>) -> Result<usize, Box<dyn Error>> {
+    let id = id.unwrap_or(1);
+    if id == 0 || id > entries.len() {
+        return Err(format!("no history entry #{}", id).into());
+    }
+    Ok(entries.len() - id)
+}
+
+fn require_file(entry: &HistoryEntry) -> Result<&Path, Box<dyn Error>> {
+    if !entry.exists() {
+        return Err(format!("{} is missing", entry.path.display()).into());
+    }
+    Ok(&entry.path)
+}
+
+/// 运行 `foamshot history` 子命令
+pub fn run(command: HistoryCommand) -> Result<(), Box<dyn Error>> {
+    let mut history = History::load()?;
+    let entries = &mut history.entries;
+    match command {
+        HistoryCommand::List { limit } => {
+            let limit = limit.unwrap_or(entries.len());
+            for (i, entry) in entries.iter().rev().enumerate().take(limit) {
+                let time = entry.time().map_or(entry.time.clone(), |t| {
+                    t.format("%Y-%m-%d %H:%M:%S").to_string()
+                });
+                let region = entry.region;
+                println!(
+                    "{:>4}  {}  {:<3}  {}x{}+{}+{}  {}  {}{}",
+                    i + 1,
+                    time,
+                    entry.format,
+                    region.width,
+                    region.height,
+                    region.x,
+                    region.y,
+                    entry.outputs.join(","),
+                    entry.path.display(),
+                    if entry.exists() { "" } else { "  (missing)" }
+                );
+            }
+        }
+        HistoryCommand::Open { id } => {
+            let entry = &entries[find(entries, id)?];
+            Command::new("xdg-open")
+                .arg(require_file(entry)?)
+                .spawn()
+                .map_err(|e| format!("启动xdg-open失败: {}", e))?;
+        }
+        HistoryCommand::CopyAgain { id } => {
+            let entry = &entries[find(entries, id)?];
+            let data =
+                fs::read(require_file(entry)?).map_err(|e| format!("读取文件失败: {}", e))?;
+            save_helper::copy_data_to_wl_clipboard(entry.mime_type(), &data)?;
+        }
+        HistoryCommand::Delete { id } => {
+            let entry = entries.remove(find(entries, id)?);
+            if entry.exists() {
+                fs::remove_file(&entry.path).map_err(|e| format!("删除文件失败: {}", e))?;
+            }
+            history.save()?;
+            println!("deleted {}", entry.path.display());
+        }
+        HistoryCommand::Prune {
+            older_than,
+            keep,
+            delete_files,
+        } => {
+            let config = HistoryConfig::load();
+            let (max_age_days, max_entries) = match (older_than, keep) {
+                (None, None) => (config.max_age_days, config.max_entries),
+                args => args,
+            };
+            if max_age_days.is_none() && max_entries.is_none() {
+                return Err("no retention policy, pass --older-than or --keep".into());
+            }
+            let removed = prune(max_age_days, max_entries, delete_files)?;
+            println!("pruned {} entries", removed.len());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unparsed_lines_survive_rewrite() {
+        let entry = r#"{"path":"/tmp/a.png","time":"2024-05-06T07:08:09+00:00","region":{"x":0,"y":0,"width":10,"height":20},"outputs":["DP-1"],"format":"png"}"#;
+        let unknown = r#"{"path":"/tmp/b.png","kind":"from a newer version"}"#;
+        let history = History::parse(&format!("{}\n\n{}\n", entry, unknown));
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(history.unparsed, [unknown]);
+        assert_eq!(
+            history.to_jsonl().unwrap(),
+            format!("{}\n{}\n", unknown, entry)
+        );
+    }
+}
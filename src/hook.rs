@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::config::FoamConfig;
+use crate::foam_outputs::FoamOutput;
+use crate::save_helper::SavedImage;
+
+/// 命令中替换为保存路径的占位符
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// 配置文件中与钩子相关的部分，如 `{"hooks": {"upload": "curl -F file=@{path} ..."}}`
+#[derive(Debug, Default, Deserialize)]
+struct HookConfig {
+    /// 名称到命令的映射
+    #[serde(default)]
+    hooks: BTreeMap<String, String>,
+}
+
+/// 保存后运行的命令，`{path}` 替换为 shell 转义后的文件路径
+#[derive(Debug, Clone)]
+pub struct Hook {
+    /// 命名钩子的名称，`--exec` 为 "exec"
+    pub name: String,
+    pub command: String,
+}
+
+/// 钩子的运行结果
+pub struct HookOutput {
+    pub name: String,
+    pub success: bool,
+    /// 退出码，被信号终止时为 None
+    pub code: Option<i32>,
+    /// 超时后被终止
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Hook {
+    /// 读取配置文件中的所有命名钩子，文件不存在时为空
+    pub fn load_named() -> Result<Vec<Hook>, Box<dyn Error>> {
+        let Some(path) = FoamConfig::config_file_path().filter(|p| p.exists()) else {
+            return Ok(Vec::new());
+        };
+        let data = fs::read(&path).map_err(|e| format!("读取配置文件失败: {}", e))?;
+        let config: HookConfig = serde_json::from_slice(&data)
+            .map_err(|e| format!("解析配置文件 {} 失败: {}", path.display(), e))?;
+        Ok(config
+            .hooks
+            .into_iter()
+            .map(|(name, command)| Hook { name, command })
+            .collect())
+    }
+
+    /// `--exec` 的命令加上 `--hook` 指定的命名钩子
+    pub fn selected(exec: &[String], names: &[String]) -> Result<Vec<Hook>, Box<dyn Error>> {
+        let mut hooks: Vec<Hook> = exec
+            .iter()
+            .map(|command| Hook {
+                name: "exec".to_string(),
+                command: command.clone(),
+            })
+            .collect();
+        if names.is_empty() {
+            return Ok(hooks);
+        }
+
+        let named = Self::load_named()?;
+        for name in names {
+            let hook = named
+                .iter()
+                .find(|h| &h.name == name)
+                .ok_or_else(|| format!("unknown hook: {}", name))?;
+            hooks.push(hook.clone());
+        }
+        Ok(hooks)
+    }
+
+    /// 以 `sh -c` 启动命令，截图信息通过环境变量传递
+    ///
+    /// 不等待时放入新的进程组并丢弃输出，foamshot 退出后继续运行
+    pub fn spawn(
+        &self,
+        image: &SavedImage,
+        outputs: &[FoamOutput],
+        detached: bool,
+    ) -> std::io::Result<Child> {
+        let path = image.path.to_string_lossy();
+        let command = self.command.replace(PATH_PLACEHOLDER, &shell_quote(&path));
+        let (x, y, width, height) = image.region;
+
+        let mut output_ids: Vec<usize> = image.subrects.iter().map(|r| r.monitor_id).collect();
+        output_ids.sort_unstable();
+        output_ids.dedup();
+        let output_names: Vec<&str> = output_ids
+            .iter()
+            .filter_map(|&id| outputs.get(id))
+            .map(|o| o.name.as_str())
+            .collect();
+
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .env("FOAMSHOT_HOOK", &self.name)
+            .env("FOAMSHOT_PATH", path.as_ref())
+            .env("FOAMSHOT_FORMAT", image.format.extension())
+            .env("FOAMSHOT_WIDTH", width.to_string())
+            .env("FOAMSHOT_HEIGHT", height.to_string())
+            .env(
+                "FOAMSHOT_REGION",
+                format!("{},{} {}x{}", x, y, width, height),
+            )
+            .env("FOAMSHOT_OUTPUTS", output_names.join(","))
+            .env("FOAMSHOT_TIMESTAMP", image.time.to_rfc3339())
+            .stdin(Stdio::null());
+        if detached {
+            cmd.stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .process_group(0);
+        } else {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        cmd.spawn()
+    }
+}
+
+/// 等待钩子结束并收集输出，超过 deadline 时终止它
+pub fn wait(name: &str, mut child: Child, deadline: Instant) -> HookOutput {
+    // 在线程中读取输出，避免管道写满时钩子阻塞
+    let stdout = child.stdout.take().map(read_pipe);
+    let stderr = child.stderr.take().map(read_pipe);
+
+    let mut timed_out = false;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Ok(status),
+            Ok(None) if Instant::now() >= deadline => {
+                timed_out = true;
+                let _ = child.kill();
+                break child.wait();
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => break Err(e),
+        }
+    };
+    // 钩子的子进程可能继续占用管道，读取输出同样不超过 deadline
+    let collect = |pipe: Option<Receiver<String>>| {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        pipe.and_then(|r| r.recv_timeout(remaining).ok())
+            .unwrap_or_default()
+    };
+    let (stdout, stderr) = (collect(stdout), collect(stderr));
+
+    match status {
+        Ok(status) => HookOutput {
+            name: name.to_string(),
+            success: status.success() && !timed_out,
+            code: status.code(),
+            timed_out,
+            stdout,
+            stderr,
+        },
+        Err(e) => HookOutput {
+            name: name.to_string(),
+            success: false,
+            code: None,
+            timed_out,
+            stdout,
+            stderr: e.to_string(),
+        },
+    }
+}
+
+fn read_pipe(mut pipe: impl Read + Send + 'static) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut data = Vec::new();
+        let _ = pipe.read_to_end(&mut data);
+        let _ = sender.send(String::from_utf8_lossy(&data).trim().to_string());
+    });
+    receiver
+}
+
+/// 用单引号包裹，内部的单引号转义为 `'\''`
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wait_kills_hook_after_deadline() {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg("echo started; sleep 5")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        let output = wait("slow", child, started + Duration::from_millis(200));
+        assert!(output.timed_out);
+        assert!(!output.success);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn wait_collects_output() {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg("echo out; echo err >&2; exit 3")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let output = wait("fail", child, Instant::now() + Duration::from_secs(5));
+        assert!(!output.timed_out);
+        assert_eq!(output.code, Some(3));
+        assert_eq!(output.stdout, "out");
+        assert_eq!(output.stderr, "err");
+    }
+}
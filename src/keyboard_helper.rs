@@ -0,0 +1,68 @@
+use std::os::fd::OwnedFd;
+
+use wayland_client::protocol::wl_keyboard;
+use xkbcommon::xkb;
+
+/// xkb 默认 keymap 下各修饰键在 mods_depressed 中的掩码
+const MOD_SHIFT: u32 = 1 << 0;
+const MOD_CTRL: u32 = 1 << 2;
+const MOD_ALT: u32 = 1 << 3;
+
+/// evdev 键码与 xkb 键码之间的偏移
+const XKB_KEYCODE_OFFSET: u32 = 8;
+
+#[derive(Default)]
+pub struct KeyboardHelper {
+    pub keyboard: Option<wl_keyboard::WlKeyboard>,
+
+    /// 当前按下的修饰键，由 wl_keyboard::Event::Modifiers 更新
+    pub mods_depressed: u32,
+
+    /// 由 wl_keyboard::Event::Keymap 创建，用于将键码转换为文字
+    xkb_state: Option<xkb::State>,
+}
+
+impl KeyboardHelper {
+    /// 加载合成器发送的 keymap
+    pub fn set_keymap(&mut self, fd: OwnedFd, size: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = unsafe {
+            xkb::Keymap::new_from_fd(
+                &context,
+                fd,
+                size as usize,
+                xkb::KEYMAP_FORMAT_TEXT_V1,
+                xkb::KEYMAP_COMPILE_NO_FLAGS,
+            )?
+        }
+        .ok_or("无法解析 keymap")?;
+        self.xkb_state = Some(xkb::State::new(&keymap));
+        Ok(())
+    }
+
+    pub fn update_modifiers(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        self.mods_depressed = depressed;
+        if let Some(state) = self.xkb_state.as_mut() {
+            state.update_mask(depressed, latched, locked, 0, 0, group);
+        }
+    }
+
+    /// 按键在当前布局和修饰键下输入的文字，控制字符返回 None
+    pub fn key_text(&self, key: u32) -> Option<String> {
+        let state = self.xkb_state.as_ref()?;
+        let text = state.key_get_utf8(xkb::Keycode::new(key + XKB_KEYCODE_OFFSET));
+        (!text.is_empty() && !text.chars().any(char::is_control)).then_some(text)
+    }
+
+    pub fn shift(&self) -> bool {
+        self.mods_depressed & MOD_SHIFT != 0
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.mods_depressed & MOD_CTRL != 0
+    }
+
+    pub fn alt(&self) -> bool {
+        self.mods_depressed & MOD_ALT != 0
+    }
+}
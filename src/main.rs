@@ -1,18 +1,48 @@
 mod action;
+mod action_bar;
+mod annotation;
 mod cairo_render;
+mod color_picker;
 mod config;
+mod context_menu;
+mod drag_helper;
 mod foam_outputs;
 mod foamshot;
+mod history;
+mod hook;
+mod keyboard_helper;
+mod measure;
 mod notify;
+mod pin;
 mod pointer_helper;
+mod project;
 mod protocols;
+mod redact;
+mod result;
 mod save_helper;
 mod select_rect;
+mod state;
+mod text_input_helper;
+mod toolbar;
+mod touch_helper;
+mod undo_stack;
 mod wayland_ctx;
+mod window_info;
 mod zwlr_screencopy_mode;
 
 fn main() {
     env_logger::init();
 
+    if let Some(command) = config::FoamConfig::history_command() {
+        if let Err(e) = history::run(command) {
+            eprintln!("foamshot history: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(args) = config::FoamConfig::notify_saved_command() {
+        notify::run_saved(args);
+        return;
+    }
     foamshot::run_main_loop();
 }
@@ -0,0 +1,218 @@
+use cairo::Context;
+
+use crate::{color_picker, wayland_ctx::WaylandCtx};
+
+const FONT_SIZE: f64 = 13.0;
+const LABEL_PADDING: f64 = 4.0;
+/// 颜色探针向外扫描的最大距离
+const PROBE_MAX_DISTANCE: i32 = 4096;
+/// 任一通道差值超过该值视为颜色变化，避免抗锯齿噪点
+const PROBE_TOLERANCE: i32 = 8;
+
+/// 测量的形状，按住 Shift 拖动时为直线
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasureKind {
+    Line,
+    Rect,
+}
+
+/// 一次测量，坐标为全局逻辑坐标
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub kind: MeasureKind,
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+    /// 起点所在输出的逻辑坐标到物理像素的换算系数
+    pub factor: (f64, f64),
+}
+
+impl Measurement {
+    pub fn new(kind: MeasureKind, start: (f64, f64), factor: (f64, f64)) -> Self {
+        Self {
+            kind,
+            start,
+            end: start,
+            factor,
+        }
+    }
+
+    fn delta(&self) -> (f64, f64) {
+        (
+            (self.end.0 - self.start.0).round(),
+            (self.end.1 - self.start.1).round(),
+        )
+    }
+
+    /// 与水平方向的夹角，逆时针为正
+    fn angle(&self) -> f64 {
+        let (dx, dy) = self.delta();
+        (-dy).atan2(dx).to_degrees()
+    }
+
+    /// 测量结果，同时给出逻辑像素和物理像素
+    pub fn label(&self) -> String {
+        let (dx, dy) = self.delta();
+        let (fx, fy) = self.factor;
+        match self.kind {
+            MeasureKind::Line => format!(
+                "{:.1} px ({:.1} phys) {:.1}°",
+                dx.hypot(dy),
+                (dx * fx).hypot(dy * fy),
+                self.angle()
+            ),
+            MeasureKind::Rect => format!(
+                "{} × {} px ({} × {} phys) {:.1}°",
+                dx.abs(),
+                dy.abs(),
+                (dx.abs() * fx).round(),
+                (dy.abs() * fy).round(),
+                self.angle()
+            ),
+        }
+    }
+
+    fn draw(&self, cr: &Context, origin: (i32, i32)) {
+        let (sx, sy) = (
+            self.start.0 - origin.0 as f64,
+            self.start.1 - origin.1 as f64,
+        );
+        let (ex, ey) = (self.end.0 - origin.0 as f64, self.end.1 - origin.1 as f64);
+
+        cr.save().unwrap();
+        cr.set_line_width(1.0);
+        cr.set_source_rgba(1.0, 0.2, 0.4, 1.0);
+        match self.kind {
+            MeasureKind::Line => {
+                cr.move_to(sx, sy);
+                cr.line_to(ex, ey);
+            }
+            MeasureKind::Rect => {
+                cr.rectangle(sx.min(ex), sy.min(ey), (ex - sx).abs(), (ey - sy).abs());
+                // 对角线用于显示角度
+                cr.stroke().unwrap();
+                cr.set_dash(&[4.0, 4.0], 0.0);
+                cr.move_to(sx, sy);
+                cr.line_to(ex, ey);
+            }
+        }
+        cr.stroke().unwrap();
+        cr.restore().unwrap();
+
+        draw_label(cr, ex + 8.0, ey + 8.0, &self.label());
+    }
+}
+
+/// 光标位置到四个方向上最近颜色变化的距离
+#[derive(Debug, Clone, Copy)]
+pub struct Probe {
+    pub x: i32,
+    pub y: i32,
+    pub left: i32,
+    pub right: i32,
+    pub up: i32,
+    pub down: i32,
+}
+
+impl Probe {
+    fn draw(&self, cr: &Context, origin: (i32, i32)) {
+        let x = (self.x - origin.0) as f64 + 0.5;
+        let y = (self.y - origin.1) as f64 + 0.5;
+
+        cr.save().unwrap();
+        cr.set_line_width(1.0);
+        cr.set_source_rgba(0.2, 0.8, 1.0, 1.0);
+        cr.move_to(x - self.left as f64, y);
+        cr.line_to(x + self.right as f64, y);
+        cr.move_to(x, y - self.up as f64);
+        cr.line_to(x, y + self.down as f64);
+        cr.stroke().unwrap();
+        cr.restore().unwrap();
+
+        draw_label(
+            cr,
+            x + 8.0,
+            y + 8.0,
+            &format!("{} × {} px", self.left + self.right, self.up + self.down),
+        );
+    }
+}
+
+/// NOTE: 测量模式的状态，绘制在所有输出的冻结画面上
+#[derive(Debug, Default)]
+pub struct MeasureState {
+    pub measurements: Vec<Measurement>,
+    pub current: Option<Measurement>,
+    pub probe: Option<Probe>,
+}
+
+impl MeasureState {
+    /// 在输出的 cairo 上下文中绘制，origin 为输出左上角的全局坐标
+    pub fn draw(&self, cr: &Context, origin: (i32, i32)) {
+        for measurement in self.measurements.iter().chain(self.current.as_ref()) {
+            measurement.draw(cr, origin);
+        }
+        if self.current.is_none()
+            && let Some(probe) = self.probe.as_ref()
+        {
+            probe.draw(cr, origin);
+        }
+    }
+}
+
+fn draw_label(cr: &Context, x: f64, y: f64, text: &str) {
+    cr.save().unwrap();
+    cr.select_font_face(
+        "sans-serif",
+        cairo::FontSlant::Normal,
+        cairo::FontWeight::Normal,
+    );
+    cr.set_font_size(FONT_SIZE);
+    let width = cr
+        .text_extents(text)
+        .map(|e| e.x_advance())
+        .unwrap_or(text.len() as f64 * FONT_SIZE / 2.0);
+
+    cr.set_source_rgba(0.15, 0.15, 0.15, 0.9);
+    cr.rectangle(
+        x,
+        y,
+        width + LABEL_PADDING * 2.0,
+        FONT_SIZE + LABEL_PADDING * 2.0,
+    );
+    cr.fill().unwrap();
+
+    cr.set_source_rgba(1.0, 1.0, 1.0, 1.0);
+    cr.move_to(x + LABEL_PADDING, y + LABEL_PADDING + FONT_SIZE * 0.85);
+    cr.show_text(text).unwrap();
+    cr.restore().unwrap();
+}
+
+/// 从 (gx, gy) 沿 (dx, dy) 方向扫描 base_canvas，返回到第一个颜色变化处的距离
+fn scan(wl_ctx: &WaylandCtx, gx: i32, gy: i32, dx: i32, dy: i32, base: (u8, u8, u8)) -> i32 {
+    let differs = |(r, g, b): (u8, u8, u8)| {
+        (r as i32 - base.0 as i32).abs() > PROBE_TOLERANCE
+            || (g as i32 - base.1 as i32).abs() > PROBE_TOLERANCE
+            || (b as i32 - base.2 as i32).abs() > PROBE_TOLERANCE
+    };
+    for distance in 1..PROBE_MAX_DISTANCE {
+        match color_picker::sample_pixel(wl_ctx, gx + dx * distance, gy + dy * distance) {
+            Some(pixel) if !differs(pixel) => {}
+            // 颜色变化或超出屏幕
+            _ => return distance - 1,
+        }
+    }
+    PROBE_MAX_DISTANCE
+}
+
+/// 计算光标处的颜色探针
+pub fn probe(wl_ctx: &WaylandCtx, gx: i32, gy: i32) -> Option<Probe> {
+    let base = color_picker::sample_pixel(wl_ctx, gx, gy)?;
+    Some(Probe {
+        x: gx,
+        y: gy,
+        left: scan(wl_ctx, gx, gy, -1, 0, base),
+        right: scan(wl_ctx, gx, gy, 1, 0, base),
+        up: scan(wl_ctx, gx, gy, 0, -1, base),
+        down: scan(wl_ctx, gx, gy, 0, 1, base),
+    })
+}
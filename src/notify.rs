@@ -1,4 +1,24 @@
-use std::process::Command;
+use std::collections::HashMap;
+use std::error::Error;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use log::{debug, warn};
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::zvariant::Value;
+use zbus::{MatchRule, message};
+
+use crate::config::{ImageType, NotifySavedArgs};
+use crate::save_helper::{self, SavedImage};
+
+const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+/// 通知中缩略图的最大边长
+const THUMBNAIL_SIZE: u32 = 256;
 
 pub enum NotificationLevel {
     Info,
@@ -7,36 +27,290 @@ pub enum NotificationLevel {
 }
 
 impl NotificationLevel {
-    fn to_urgency(&self) -> &str {
+    /// hints 中的 urgency，0 为 low，1 为 normal，2 为 critical
+    fn to_urgency(&self) -> u8 {
+        match self {
+            NotificationLevel::Info => 0,
+            NotificationLevel::Warn => 1,
+            NotificationLevel::Error => 2,
+        }
+    }
+}
+
+/// 保存通知上的按钮
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyAction {
+    Open,
+    OpenFolder,
+    CopyAgain,
+    Delete,
+}
+
+impl NotifyAction {
+    const ALL: [NotifyAction; 4] = [
+        NotifyAction::Open,
+        NotifyAction::OpenFolder,
+        NotifyAction::CopyAgain,
+        NotifyAction::Delete,
+    ];
+
+    fn key(self) -> &'static str {
+        match self {
+            NotifyAction::Open => "open",
+            NotifyAction::OpenFolder => "open-folder",
+            NotifyAction::CopyAgain => "copy-again",
+            NotifyAction::Delete => "delete",
+        }
+    }
+
+    fn label(self) -> &'static str {
         match self {
-            NotificationLevel::Info => "low",
-            NotificationLevel::Warn => "normal",
-            NotificationLevel::Error => "critical",
+            NotifyAction::Open => "Open",
+            NotifyAction::OpenFolder => "Open folder",
+            NotifyAction::CopyAgain => "Copy again",
+            NotifyAction::Delete => "Delete",
         }
     }
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.key() == key)
+    }
 }
 
-pub fn send<T: ToString + std::convert::AsRef<std::ffi::OsStr>>(
+/// NOTE: 通过会话总线发送的保存通知，在后台的 `foamshot notify-saved` 进程中处理按钮
+struct SavedNotification {
+    /// 在 Notify 之前订阅的通知信号
+    signals: MessageIterator,
+    id: u32,
+    images: Vec<(PathBuf, ImageType)>,
+}
+
+pub fn send(
     level: NotificationLevel,
     summary: &str,
-    body: T,
-    image_path: T,
+    body: impl AsRef<str>,
+    icon: &str,
     allow_notify: bool,
 ) {
     if !allow_notify {
         return;
     }
-    let urgency = level.to_urgency();
-
-    Command::new("notify-send")
-        .arg("--urgency")
-        .arg(urgency)
-        .arg(summary)
-        .arg(body.to_string())
-        .arg("-i")
-        .arg(image_path)
-        .arg("-a")
-        .arg(env!("CARGO_PKG_NAME"))
-        .output()
-        .ok();
+    let result = Connection::session()
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|conn| notify(&conn, &level, summary, body.as_ref(), icon, None, &[]));
+    if let Err(e) = result {
+        warn!("发送通知失败: {}", e);
+    }
+}
+
+/// 在独立的进程组中启动 `foamshot notify-saved` 发送保存通知，不等待它结束
+///
+/// 等待按钮期间不会阻塞截图进程退出，也不会占用调用者的 stdout
+pub fn spawn_saved(body: &str, images: &[SavedImage], timeout: Duration, allow_notify: bool) {
+    if !allow_notify || images.is_empty() {
+        return;
+    }
+    let result = std::env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .arg("notify-saved")
+            .arg("--body")
+            .arg(body)
+            .arg("--timeout")
+            .arg(timeout.as_secs().to_string())
+            .args(images.iter().map(|image| &image.path))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+    });
+    if let Err(e) = result {
+        warn!("启动通知进程失败: {}", e);
+    }
+}
+
+/// 运行 `foamshot notify-saved`：发送通知并在超时前处理一次按钮
+pub fn run_saved(args: NotifySavedArgs) {
+    let images: Vec<(PathBuf, ImageType)> = args
+        .files
+        .into_iter()
+        .map(|path| {
+            let format = path
+                .extension()
+                .and_then(|ext| ImageType::from_str(&ext.to_string_lossy(), true).ok())
+                .unwrap_or_default();
+            (path, format)
+        })
+        .collect();
+    if let Some(notification) = send_saved(&args.body, images) {
+        notification.wait_actions(Duration::from_secs(args.timeout));
+    }
+}
+
+/// 发送带缩略图和 Open、Open folder、Copy again、Delete 按钮的保存通知
+fn send_saved(body: &str, images: Vec<(PathBuf, ImageType)>) -> Option<SavedNotification> {
+    let (first, _) = images.first()?;
+    let thumbnail = thumbnail(first);
+    let result = Connection::session()
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|conn| {
+            // 先订阅信号，避免错过发送后立即点击的按钮
+            let rule = MatchRule::builder()
+                .msg_type(message::Type::Signal)
+                .interface(NOTIFICATIONS_NAME)?
+                .build();
+            let signals = MessageIterator::for_match_rule(rule, &conn, None)?;
+            let id = notify(
+                &conn,
+                &NotificationLevel::Info,
+                "image_saved",
+                body,
+                "image-x-generic",
+                thumbnail.as_ref(),
+                &NotifyAction::ALL,
+            )?;
+            Ok((signals, id))
+        });
+    match result {
+        Ok((signals, id)) => Some(SavedNotification {
+            signals,
+            id,
+            images,
+        }),
+        Err(e) => {
+            warn!("发送通知失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 调用 Notify，返回通知 id
+fn notify(
+    conn: &Connection,
+    level: &NotificationLevel,
+    summary: &str,
+    body: &str,
+    icon: &str,
+    thumbnail: Option<&image::RgbaImage>,
+    actions: &[NotifyAction],
+) -> Result<u32, Box<dyn Error>> {
+    let actions: Vec<&str> = actions
+        .iter()
+        .flat_map(|action| [action.key(), action.label()])
+        .collect();
+    let reply = conn.call_method(
+        Some(NOTIFICATIONS_NAME),
+        NOTIFICATIONS_PATH,
+        Some(NOTIFICATIONS_NAME),
+        "Notify",
+        &(
+            env!("CARGO_PKG_NAME"),
+            0u32,
+            icon,
+            summary,
+            body,
+            actions,
+            hints(level, thumbnail),
+            -1i32,
+        ),
+    )?;
+    Ok(reply.body().deserialize::<u32>()?)
+}
+
+/// urgency 和缩略图，image-data 的签名为 `(iiibiiay)`
+fn hints(
+    level: &NotificationLevel,
+    thumbnail: Option<&image::RgbaImage>,
+) -> HashMap<&'static str, Value<'static>> {
+    let mut hints = HashMap::new();
+    hints.insert("urgency", Value::U8(level.to_urgency()));
+    if let Some(image) = thumbnail {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        let data = (width, height, width * 4, true, 8, 4, image.as_raw().clone());
+        hints.insert("image-data", Value::from(data));
+    }
+    hints
+}
+
+/// 缩小后的 RGBA 缩略图，矢量格式等无法解码时没有缩略图
+fn thumbnail(path: &Path) -> Option<image::RgbaImage> {
+    let image = image::open(path)
+        .inspect_err(|e| debug!("无法生成通知缩略图: {}", e))
+        .ok()?;
+    Some(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8())
+}
+
+impl SavedNotification {
+    /// 在 timeout 内等待按钮被点击，处理一次后或通知关闭时返回
+    fn wait_actions(self, timeout: Duration) {
+        // 阻塞的信号迭代器没有超时，在线程中读取
+        let (sender, receiver) = mpsc::channel();
+        let id = self.id;
+        std::thread::spawn(move || {
+            for message in self.signals {
+                let message = match message {
+                    Ok(message) => message,
+                    Err(e) => {
+                        warn!("读取通知信号失败: {}", e);
+                        return;
+                    }
+                };
+                let header = message.header();
+                let event = match header.member().map(|m| m.as_str()) {
+                    Some("ActionInvoked") => message
+                        .body()
+                        .deserialize::<(u32, String)>()
+                        .ok()
+                        .filter(|(signal_id, _)| *signal_id == id)
+                        .map(|(_, key)| NotifyAction::from_key(&key)),
+                    Some("NotificationClosed") => message
+                        .body()
+                        .deserialize::<(u32, u32)>()
+                        .ok()
+                        .filter(|(signal_id, _)| *signal_id == id)
+                        .map(|_| None),
+                    _ => None,
+                };
+                if let Some(action) = event
+                    && (sender.send(action).is_err() || action.is_none())
+                {
+                    return;
+                }
+            }
+        });
+
+        // 线程只发送本通知的按钮或关闭事件
+        if let Ok(Some(action)) = receiver.recv_timeout(timeout) {
+            run_action(&self.images, action);
+        }
+    }
+}
+
+/// 打开和复制使用第一个文件，删除本次保存的所有文件
+fn run_action(images: &[(PathBuf, ImageType)], action: NotifyAction) {
+    let Some((path, format)) = images.first() else {
+        return;
+    };
+    debug!("notification action: {:?}", action);
+    let result = match action {
+        NotifyAction::Open => xdg_open(path),
+        NotifyAction::OpenFolder => xdg_open(path.parent().unwrap_or(Path::new("."))),
+        NotifyAction::CopyAgain => std::fs::read(path)
+            .map_err(|e| format!("读取文件失败: {}", e).into())
+            .and_then(|data| save_helper::copy_data_to_wl_clipboard(format.mime_type(), &data)),
+        NotifyAction::Delete => images.iter().try_for_each(|(path, _)| {
+            std::fs::remove_file(path).map_err(|e| format!("删除文件失败: {}", e).into())
+        }),
+    };
+    if let Err(e) = result {
+        warn!("{} {} error: {}", action.key(), path.display(), e);
+    }
+}
+
+fn xdg_open(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    Command::new("xdg-open")
+        .arg(path)
+        .spawn()
+        .map_err(|e| format!("启动xdg-open失败: {}", e))?;
+    Ok(())
 }
@@ -0,0 +1,205 @@
+use cairo::{Format as CairoFormat, ImageSurface};
+use smithay_client_toolkit::shm::{Shm, slot::SlotPool};
+use wayland_client::{
+    QueueHandle,
+    protocol::{wl_compositor, wl_output, wl_shm::Format, wl_surface},
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{self, Layer},
+    zwlr_layer_surface_v1::{Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+};
+
+use crate::foamshot::FoamShot;
+
+const ZOOM_STEP: f64 = 1.1;
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 8.0;
+const OPACITY_STEP: f64 = 0.1;
+const MIN_OPACITY: f64 = 0.2;
+/// 两次点击间隔小于该值（毫秒）视为双击
+const DOUBLE_CLICK_MS: u32 = 400;
+
+/// 钉图 surface 的 user data，与选区遮罩层的输出序号区分
+#[derive(Debug, Clone, Copy)]
+pub struct PinSurface;
+
+/// NOTE: 钉在屏幕上的截图，使用 overlay 层的 layer-shell surface 始终显示在其他窗口之上
+///
+/// 通过修改 margin 拖动位置，滚轮缩放，Ctrl+滚轮调整不透明度
+pub struct PinWindow {
+    pub surface: wl_surface::WlSurface,
+    pub layer_surface: ZwlrLayerSurfaceV1,
+    pool: SlotPool,
+    image: ImageSurface,
+    /// 窗口左上角在输出中的逻辑坐标
+    x: i32,
+    y: i32,
+    /// 所在输出的逻辑尺寸，拖动时不超出输出
+    bounds: (i32, i32),
+    scale: i32,
+    pub zoom: f64,
+    pub opacity: f64,
+    configured: bool,
+    /// 光标在窗口内的坐标
+    pointer: (f64, f64),
+    /// 拖动时按下位置在窗口内的坐标
+    drag: Option<(f64, f64)>,
+    /// 上一次按下的时间，用于检测双击
+    last_press: Option<u32>,
+}
+
+impl PinWindow {
+    /// 在 output 的 (x, y) 处显示 image，image 的一个像素对应一个逻辑像素
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        compositor: &wl_compositor::WlCompositor,
+        layer_shell: &zwlr_layer_shell_v1::ZwlrLayerShellV1,
+        shm: &Shm,
+        qh: &QueueHandle<FoamShot>,
+        output: &wl_output::WlOutput,
+        bounds: (i32, i32),
+        scale: i32,
+        position: (i32, i32),
+        image: ImageSurface,
+        opacity: f64,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let size = (image.width() * image.height() * 4 * scale * scale) as usize;
+        let pool = SlotPool::new(size.max(4), shm)?;
+
+        let surface = compositor.create_surface(qh, PinSurface);
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            Some(output),
+            Layer::Overlay,
+            "foamshot-pin".to_string(),
+            qh,
+            PinSurface,
+        );
+        layer_surface.set_anchor(Anchor::Top | Anchor::Left);
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::OnDemand);
+
+        let pin = Self {
+            surface,
+            layer_surface,
+            pool,
+            image,
+            x: position.0,
+            y: position.1,
+            bounds,
+            scale: scale.max(1),
+            zoom: 1.0,
+            opacity: opacity.clamp(MIN_OPACITY, 1.0),
+            configured: false,
+            pointer: (0.0, 0.0),
+            drag: None,
+            last_press: None,
+        };
+        pin.apply_geometry();
+        pin.surface.commit();
+        Ok(pin)
+    }
+
+    /// 缩放后的逻辑尺寸
+    fn size(&self) -> (i32, i32) {
+        (
+            ((self.image.width() as f64 * self.zoom).round() as i32).max(1),
+            ((self.image.height() as f64 * self.zoom).round() as i32).max(1),
+        )
+    }
+
+    fn apply_geometry(&self) {
+        let (w, h) = self.size();
+        self.layer_surface.set_size(w as u32, h as u32);
+        self.layer_surface.set_margin(self.y, 0, 0, self.x);
+    }
+
+    pub fn configure(&mut self) {
+        self.configured = true;
+        self.draw();
+    }
+
+    /// 绘制当前缩放和不透明度下的截图
+    pub fn draw(&mut self) {
+        if !self.configured {
+            return;
+        }
+        let (w, h) = self.size();
+        let (bw, bh) = (w * self.scale, h * self.scale);
+        let Ok((buffer, canvas)) = self.pool.create_buffer(bw, bh, bw * 4, Format::Argb8888) else {
+            return;
+        };
+        canvas.fill(0);
+
+        let cairo_surface = unsafe {
+            ImageSurface::create_for_data_unsafe(
+                canvas.as_mut_ptr(),
+                CairoFormat::ARgb32,
+                bw,
+                bh,
+                bw * 4,
+            )
+            .unwrap()
+        };
+        {
+            let cr = cairo::Context::new(&cairo_surface).unwrap();
+            cr.scale(self.zoom * self.scale as f64, self.zoom * self.scale as f64);
+            cr.set_source_surface(&self.image, 0.0, 0.0).unwrap();
+            cr.paint_with_alpha(self.opacity).unwrap();
+        }
+        drop(cairo_surface);
+
+        buffer.attach_to(&self.surface).unwrap();
+        self.surface.damage_buffer(0, 0, bw, bh);
+        self.surface.set_buffer_scale(self.scale);
+        self.surface.commit();
+    }
+
+    /// 以滚动格数缩放，向上滚动放大
+    pub fn zoom_by(&mut self, steps: i32) {
+        self.zoom = (self.zoom * ZOOM_STEP.powi(-steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.apply_geometry();
+        self.draw();
+    }
+
+    /// 以滚动格数调整不透明度，向上滚动更不透明
+    pub fn fade_by(&mut self, steps: i32) {
+        self.opacity = (self.opacity - steps as f64 * OPACITY_STEP).clamp(MIN_OPACITY, 1.0);
+        self.draw();
+    }
+
+    /// 按下时开始拖动，返回是否为双击
+    pub fn press(&mut self, time: u32) -> bool {
+        let double = self
+            .last_press
+            .is_some_and(|last| time.wrapping_sub(last) < DOUBLE_CLICK_MS);
+        self.last_press = Some(time);
+        self.drag = Some(self.pointer);
+        double
+    }
+
+    pub fn release(&mut self) {
+        self.drag = None;
+    }
+
+    /// 拖动时光标在窗口内的位置变化即为窗口需要移动的距离
+    pub fn motion(&mut self, pos: (f64, f64)) {
+        self.pointer = pos;
+        let Some(start) = self.drag else {
+            return;
+        };
+        let (w, h) = self.size();
+        let x = self.x + (pos.0 - start.0).round() as i32;
+        let y = self.y + (pos.1 - start.1).round() as i32;
+        // margin 不能为负，窗口保持在输出内
+        let (x, y) = (
+            x.clamp(0, (self.bounds.0 - w).max(0)),
+            y.clamp(0, (self.bounds.1 - h).max(0)),
+        );
+        if (x, y) != (self.x, self.y) {
+            self.x = x;
+            self.y = y;
+            self.apply_geometry();
+            self.surface.commit();
+        }
+    }
+}
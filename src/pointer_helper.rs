@@ -8,6 +8,14 @@ use wayland_protocols::wp::cursor_shape::v1::client::{
 
 use crate::foamshot::FoamShot;
 
+/// linux/input-event-codes.h 中的鼠标按键
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+
+/// 滚轮一格对应的 axis 值
+const SCROLL_STEP: f64 = 10.0;
+
 #[derive(Default)]
 pub struct PointerHelper {
     pub pointer: Option<wl_pointer::WlPointer>,
@@ -25,12 +33,51 @@ pub struct PointerHelper {
 
     /// 是否在按下
     pub is_pressing: bool,
+    /// 开始当前操作的按键，只有该按键松开时结束操作
+    pub active_button: Option<u32>,
+    /// 按下时未经磁吸的位置，用于区分单击与拖动
+    pub press_pos: Option<(f64, f64)>,
+
+    /// 未满一格的滚动距离，触摸板会发送很多小的 axis 事件
+    pub scroll_accum: f64,
 
     /// 最新的surface enter 序列号
     pub serial: u32,
+    /// 最新的按键按下序列号，开始拖放时需要
+    pub button_serial: u32,
 }
 
 impl PointerHelper {
+    pub fn begin_press(&mut self, button: u32) {
+        self.is_pressing = true;
+        self.active_button = Some(button);
+        self.press_pos = self.g_current_pos;
+    }
+
+    /// 按下与当前位置的距离小于 min_distance 时视为单击
+    pub fn is_click(&self, min_distance: f64) -> bool {
+        match (self.press_pos, self.g_current_pos) {
+            (Some((sx, sy)), Some((cx, cy))) => (cx - sx).hypot(cy - sy) < min_distance,
+            _ => false,
+        }
+    }
+
+    /// 累积滚动距离，每满一格返回对应的格数，向下滚动为正
+    pub fn scroll_steps(&mut self, value: f64) -> Option<i32> {
+        self.scroll_accum += value;
+        let steps = (self.scroll_accum / SCROLL_STEP).trunc();
+        if steps == 0.0 {
+            return None;
+        }
+        self.scroll_accum -= steps * SCROLL_STEP;
+        Some(steps as i32)
+    }
+
+    pub fn reset_press(&mut self) {
+        self.is_pressing = false;
+        self.active_button = None;
+    }
+
     /// 确保cursor_shape_device存在
     #[inline(always)]
     fn ensure_cursor_device(
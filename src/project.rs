@@ -0,0 +1,231 @@
+use std::error::Error;
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+
+use image::{ExtendedColorType, ImageEncoder, ImageFormat, codecs::png};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::annotation::Annotation;
+use crate::select_rect::SelectRect;
+use crate::state::OutputLayout;
+use crate::wayland_ctx::WaylandCtx;
+
+/// 文件头的魔数
+const MAGIC: &[u8; 4] = b"FOAM";
+const VERSION: u32 = 1;
+
+/// 项目中的一个输出，截图数据按顺序以 PNG 格式存放在头部之后
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProjectOutput {
+    layout: OutputLayout,
+    /// PNG 数据的字节数
+    size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProjectHeader {
+    version: u32,
+    outputs: Vec<ProjectOutput>,
+    /// 全局选区 (sx, sy, ex, ey)
+    global_rect: Option<(i32, i32, i32, i32)>,
+    annotations: Vec<Annotation>,
+}
+
+/// NOTE: `.foam` 项目文件，保存所有输出的原始截图、输出布局、选区和标注，可以重新打开编辑
+///
+/// 文件结构：`FOAM` + 头部长度 (u32 LE) + JSON 头部 + 每个输出的 PNG 数据
+pub struct Project {
+    header: ProjectHeader,
+    /// 每个输出的原始画布，与 base_canvas 相同为 B,G,R,A 排列
+    canvases: Vec<Vec<u8>>,
+}
+
+impl Project {
+    /// 从当前的冻结画面、选区和标注创建项目
+    pub fn capture(wl_ctx: &WaylandCtx) -> Result<Self, Box<dyn Error>> {
+        let outputs = wl_ctx.foam_outputs.as_ref().ok_or("输出设备未初始化")?;
+        let base_canvas = wl_ctx.scm.base_canvas.as_ref().ok_or("画布未初始化")?;
+
+        let mut canvases = Vec::new();
+        for (i, _) in outputs.iter().enumerate() {
+            let canvas = base_canvas
+                .get(&i)
+                .ok_or_else(|| format!("显示器{}的画布未初始化", i))?;
+            canvases.push(canvas.clone());
+        }
+
+        Ok(Self {
+            header: ProjectHeader {
+                version: VERSION,
+                outputs: OutputLayout::from_outputs(outputs)
+                    .into_iter()
+                    .map(|layout| ProjectOutput { layout, size: 0 })
+                    .collect(),
+                global_rect: wl_ctx
+                    .global_rect
+                    .as_ref()
+                    .map(|r| (r.sx, r.sy, r.ex, r.ey)),
+                annotations: wl_ctx.annotator.annotations.clone(),
+            },
+            canvases,
+        })
+    }
+
+    /// 写入项目文件，已存在时覆盖
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut header = self.header.clone();
+        let mut blobs = Vec::new();
+        for (output, canvas) in header.outputs.iter_mut().zip(&self.canvases) {
+            let blob = encode_canvas(canvas, output.layout.width, output.layout.height)?;
+            output.size = blob.len() as u64;
+            blobs.push(blob);
+        }
+
+        let json = serde_json::to_vec(&header)?;
+        let mut file = fs::File::create(path).map_err(|e| format!("创建项目文件失败: {}", e))?;
+        file.write_all(MAGIC)?;
+        file.write_all(&(json.len() as u32).to_le_bytes())?;
+        file.write_all(&json)?;
+        for blob in &blobs {
+            file.write_all(blob)?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let data = fs::read(path).map_err(|e| format!("读取项目文件失败: {}", e))?;
+        let mut reader = Cursor::new(data);
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err("不是 foamshot 项目文件".into());
+        }
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let json = read_chunk(&mut reader, u32::from_le_bytes(len) as u64)?;
+        let header: ProjectHeader = serde_json::from_slice(&json)?;
+        if header.version > VERSION {
+            return Err(format!("不支持的项目文件版本: {}", header.version).into());
+        }
+
+        let mut canvases = Vec::new();
+        for output in &header.outputs {
+            let blob = read_chunk(&mut reader, output.size)?;
+            canvases.push(decode_canvas(&blob, &output.layout)?);
+        }
+
+        Ok(Self { header, canvases })
+    }
+
+    /// 用项目中的截图替换当前画面，并恢复选区和标注
+    ///
+    /// 截图优先按输出的名称、位置、尺寸和缩放完全匹配，找不到时退回只按名称和尺寸匹配，
+    /// 输出整体移动过时选区和标注随之平移
+    pub fn apply(mut self, wl_ctx: &mut WaylandCtx) -> Result<(), Box<dyn Error>> {
+        let outputs = wl_ctx.foam_outputs.as_ref().ok_or("输出设备未初始化")?;
+        let current = OutputLayout::from_outputs(outputs);
+        let base_canvas = wl_ctx.scm.base_canvas.as_mut().ok_or("画布未初始化")?;
+
+        let mut offsets = Vec::new();
+        for (i, layout) in current.iter().enumerate() {
+            let stored = &self.header.outputs;
+            let index = stored
+                .iter()
+                .position(|o| &o.layout == layout)
+                .or_else(|| {
+                    stored.iter().position(|o| {
+                        o.layout.name == layout.name
+                            && (o.layout.width, o.layout.height) == (layout.width, layout.height)
+                    })
+                })
+                .ok_or_else(|| format!("输出 {} 与项目文件中的布局不一致", layout.name))?;
+            let saved = &stored[index].layout;
+            offsets.push((
+                layout.global_x - saved.global_x,
+                layout.global_y - saved.global_y,
+            ));
+            base_canvas.insert(i, self.canvases[index].clone());
+        }
+
+        // 各输出的位移不一致时无法确定标注属于哪个输出，保持原坐标
+        let (dx, dy) = offsets.first().copied().unwrap_or_default();
+        if offsets.iter().all(|&o| o == (dx, dy)) {
+            self.translate(dx, dy);
+        } else {
+            warn!("outputs moved unevenly since the project was saved");
+        }
+
+        wl_ctx.global_rect = self
+            .header
+            .global_rect
+            .map(|(sx, sy, ex, ey)| SelectRect::new(sx, sy, ex, ey));
+        wl_ctx.annotator.restore(self.header.annotations);
+        Ok(())
+    }
+
+    /// 将选区和标注整体平移
+    fn translate(&mut self, dx: i32, dy: i32) {
+        if (dx, dy) == (0, 0) {
+            return;
+        }
+        if let Some((sx, sy, ex, ey)) = self.header.global_rect.as_mut() {
+            *sx += dx;
+            *sy += dy;
+            *ex += dx;
+            *ey += dy;
+        }
+        for annotation in &mut self.header.annotations {
+            for point in &mut annotation.points {
+                point.0 += dx as f64;
+                point.1 += dy as f64;
+            }
+        }
+    }
+}
+
+/// 读取 len 字节，长度来自文件本身，分配前先确认剩余数据足够
+fn read_chunk(reader: &mut Cursor<Vec<u8>>, len: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+    let remaining = reader.get_ref().len() as u64 - reader.position();
+    if len > remaining {
+        return Err("项目文件已损坏: 数据长度超出文件大小".into());
+    }
+    let mut chunk = vec![0u8; len as usize];
+    reader.read_exact(&mut chunk)?;
+    Ok(chunk)
+}
+
+/// 将 B,G,R,A 排列的画布无损编码为 PNG
+fn encode_canvas(canvas: &[u8], width: i32, height: i32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let rgba: Vec<u8> = canvas
+        .chunks_exact(4)
+        .flat_map(|p| [p[2], p[1], p[0], p[3]])
+        .collect();
+
+    let mut blob = Vec::new();
+    png::PngEncoder::new_with_quality(
+        &mut blob,
+        png::CompressionType::Best,
+        png::FilterType::Adaptive,
+    )
+    .write_image(&rgba, width as u32, height as u32, ExtendedColorType::Rgba8)
+    .map_err(|e| format!("编码截图失败: {}", e))?;
+    Ok(blob)
+}
+
+fn decode_canvas(blob: &[u8], layout: &OutputLayout) -> Result<Vec<u8>, Box<dyn Error>> {
+    let image = image::load_from_memory_with_format(blob, ImageFormat::Png)
+        .map_err(|e| format!("解码截图失败: {}", e))?
+        .into_rgba8();
+    if (image.width() as i32, image.height() as i32) != (layout.width, layout.height) {
+        return Err(format!("输出 {} 的截图尺寸不正确", layout.name).into());
+    }
+    Ok(image
+        .as_raw()
+        .chunks_exact(4)
+        .flat_map(|p| [p[2], p[1], p[0], p[3]])
+        .collect())
+}
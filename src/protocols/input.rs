@@ -0,0 +1,429 @@
+use wayland_client::protocol::wl_pointer;
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
+
+use crate::{
+    action::{Action, EditAction, ExitAction, MeasureAction, PickAction},
+    annotation::Tool,
+    context_menu::MenuItem,
+    foamshot::FoamShot,
+    measure::MeasureKind,
+    pointer_helper::{BTN_LEFT, BTN_MIDDLE, BTN_RIGHT},
+};
+
+/// NOTE: 鼠标与触摸共用的输入处理，触摸按下/松开视为左键
+impl FoamShot {
+    /// 按键按下，g_current_pos 需要已经更新
+    pub(crate) fn handle_press(&mut self, button: u32) {
+        // 点击任意位置结束文字输入
+        if let Action::OnEdit(EditAction::Typing) = self.action {
+            self.wayland_ctx.finish_text();
+            self.action = Action::OnEdit(EditAction::None);
+        }
+        // 点击任意位置取消另存为
+        if let Action::OnEdit(EditAction::Rename) = self.action {
+            self.wayland_ctx.finish_rename(false);
+            self.action = Action::OnEdit(EditAction::None);
+        }
+
+        // 菜单打开时点击菜单项执行操作，点击其他位置关闭菜单
+        if let Some(menu) = self.wayland_ctx.context_menu.as_ref() {
+            let item = self
+                .wayland_ctx
+                .pointer_helper
+                .g_current_pos
+                .and_then(|(x, y)| menu.item_at(x, y))
+                .map(|i| menu.items[i]);
+            self.wayland_ctx.close_context_menu();
+            if let (BTN_LEFT, Some(item)) = (button, item) {
+                self.run_menu_item(item);
+            }
+            return;
+        }
+
+        // 点击工具栏切换工具、颜色和线宽
+        if let (BTN_LEFT, Action::OnEdit(EditAction::None)) = (button, self.action)
+            && let Some(item) = self.wayland_ctx.toolbar_item_at_pointer()
+        {
+            if let Some(item) = item {
+                self.wayland_ctx.apply_toolbar_item(item);
+            }
+            return;
+        }
+
+        // 点击操作栏执行对应的操作
+        if let (BTN_LEFT, Action::OnEdit(EditAction::None)) = (button, self.action)
+            && let Some(item) = self.wayland_ctx.action_bar_item_at_pointer()
+        {
+            if let Some(item) = item {
+                self.run_menu_item(item);
+            }
+            return;
+        }
+
+        match (button, self.action) {
+            // 绘制时右键取消当前选区
+            (BTN_RIGHT, Action::OnDraw) => {
+                self.wayland_ctx.pointer_helper.reset_press();
+                self.wayland_ctx.cancel_global_rect();
+                self.action = Action::WaitPointerPress;
+                return;
+            }
+            (BTN_RIGHT, Action::Pick(PickAction::OnDraw)) => {
+                self.wayland_ctx.pointer_helper.reset_press();
+                self.wayland_ctx.cancel_global_rect();
+                self.action = Action::Pick(PickAction::WaitPress);
+                return;
+            }
+            // 测量模式下右键取消当前测量或删除上一次测量
+            (BTN_RIGHT, Action::Measure(_)) => {
+                self.wayland_ctx.pointer_helper.reset_press();
+                self.wayland_ctx.undo_measure();
+                self.action = Action::Measure(MeasureAction::WaitPress);
+                return;
+            }
+            // 编辑模式下右键打开菜单
+            (BTN_RIGHT, Action::OnEdit(EditAction::None)) => {
+                self.wayland_ctx.open_context_menu();
+                return;
+            }
+            // 按住 Shift 在选区内拖动时，把截图拖放到其他程序
+            (BTN_LEFT, Action::OnEdit(EditAction::None))
+                if self.wayland_ctx.keyboard_helper.shift() && self.pointer_in_selection() =>
+            {
+                self.start_drag();
+                return;
+            }
+            // 中键在任意位置拖动整个选区
+            (BTN_MIDDLE, Action::OnEdit(EditAction::None))
+                if self.wayland_ctx.global_rect.is_some() =>
+            {
+                self.wayland_ctx.pointer_helper.begin_press(button);
+                self.wayland_ctx.pointer_helper.g_start_pos =
+                    self.wayland_ctx.pointer_helper.g_current_pos;
+                self.action = Action::OnEdit(EditAction::Move);
+                self.wayland_ctx.begin_edit_gesture();
+                return;
+            }
+            (BTN_LEFT, _) => {}
+            _ => return,
+        }
+
+        self.wayland_ctx.pointer_helper.begin_press(button);
+        let object = match self.action {
+            Action::OnEdit(EditAction::None) => self.wayland_ctx.object_at_pointer(),
+            _ => None,
+        };
+        match self.action {
+            Action::WaitPointerPress => {
+                self.wayland_ctx.begin_draw();
+                self.action = Action::OnDraw;
+            }
+            // 按住 Ctrl 在编辑模式下拖动时，提交当前选区并开始绘制新的选区
+            Action::OnEdit(EditAction::None) if self.wayland_ctx.keyboard_helper.ctrl() => {
+                self.wayland_ctx.commit_global_rect();
+                self.wayland_ctx.begin_draw();
+                self.action = Action::OnDraw;
+            }
+            // 没有选中工具时点击标注选中并拖动，选中标注的控制点用于缩放
+            Action::OnEdit(EditAction::None) if object.is_some() => {
+                if let Some((index, handle)) = object {
+                    self.wayland_ctx.begin_object_drag(index, handle);
+                }
+                self.action = Action::OnEdit(EditAction::Object);
+            }
+            // 文字工具单击放置文字框
+            Action::OnEdit(EditAction::None)
+                if self.wayland_ctx.annotator.tool == Some(Tool::Text) =>
+            {
+                self.wayland_ctx.begin_text();
+                self.action = Action::OnEdit(EditAction::Typing);
+            }
+            // 选中标注工具时拖动绘制标注
+            Action::OnEdit(EditAction::None) if self.wayland_ctx.annotator.tool.is_some() => {
+                self.wayland_ctx.begin_annotation();
+                self.action = Action::OnEdit(EditAction::Annotate);
+            }
+            Action::Pick(PickAction::WaitPress) => {
+                self.wayland_ctx.pointer_helper.start_index = self.wayland_ctx.current_index;
+                self.wayland_ctx.pointer_helper.g_start_pos =
+                    self.wayland_ctx.pointer_helper.g_current_pos;
+                self.wayland_ctx.generate_rects_and_send_frame();
+                self.action = Action::Pick(PickAction::OnDraw);
+            }
+            Action::Measure(MeasureAction::WaitPress) => {
+                let kind = if self.wayland_ctx.keyboard_helper.shift() {
+                    MeasureKind::Line
+                } else {
+                    MeasureKind::Rect
+                };
+                self.wayland_ctx.begin_measure(kind);
+                self.action = Action::Measure(MeasureAction::OnDraw);
+            }
+            Action::OnEdit(EditAction::None) => {
+                self.wayland_ctx.deselect_object();
+                self.wayland_ctx.pointer_helper.g_start_pos =
+                    self.wayland_ctx.pointer_helper.g_current_pos;
+
+                if let Some(current_pos) = self.wayland_ctx.pointer_helper.g_current_pos {
+                    if let Some(global_rect) = self.wayland_ctx.global_rect.as_ref() {
+                        let hit_region = global_rect.hit_region(
+                            current_pos.0 as i32,
+                            current_pos.1 as i32,
+                            self.wayland_ctx.hit_threshold(),
+                        );
+                        self.action = Action::OnEdit(hit_region);
+                        self.wayland_ctx.begin_edit_gesture();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 按键松开，只处理开始本次操作的按键
+    pub(crate) fn handle_release(&mut self, button: u32) {
+        if self.wayland_ctx.pointer_helper.active_button != Some(button) {
+            return;
+        }
+        self.wayland_ctx.pointer_helper.reset_press();
+
+        match self.action {
+            Action::Pick(PickAction::OnDraw) => {
+                self.wayland_ctx.pointer_helper.g_end_pos =
+                    self.wayland_ctx.pointer_helper.g_current_pos;
+                self.action = Action::Pick(PickAction::Done);
+                return;
+            }
+            Action::Pick(_) => return,
+            Action::Measure(_) => {
+                // --json 时测量结果在退出时的 JSON 中输出
+                if let Some(label) = self.wayland_ctx.finish_measure()
+                    && !self.wayland_ctx.config.json
+                {
+                    println!("{}", label);
+                }
+                self.action = Action::Measure(MeasureAction::WaitPress);
+                return;
+            }
+            _ => {}
+        }
+
+        if let Action::OnEdit(EditAction::Typing) = self.action {
+            return;
+        }
+
+        if let Action::OnEdit(EditAction::Annotate) = self.action {
+            self.wayland_ctx.finish_annotation();
+            self.action = Action::OnEdit(EditAction::None);
+            return;
+        }
+
+        if let Action::OnEdit(EditAction::Object) = self.action {
+            self.wayland_ctx.finish_object_drag();
+            self.action = Action::OnEdit(EditAction::None);
+            return;
+        }
+
+        if let Action::OnEdit(_) = self.action {
+            self.wayland_ctx.end_edit_gesture();
+        }
+
+        if self.action == Action::OnDraw {
+            self.wayland_ctx.pointer_helper.end_index = self.wayland_ctx.current_index;
+            self.wayland_ctx.pointer_helper.g_end_pos =
+                self.wayland_ctx.pointer_helper.g_current_pos;
+
+            // 没有拖动时截取光标下的窗口或输出
+            if self
+                .wayland_ctx
+                .pointer_helper
+                .is_click(self.wayland_ctx.config.min_drag)
+            {
+                self.wayland_ctx.select_under_pointer();
+            }
+
+            // 按住 Ctrl 松开时提交选区，继续添加下一个
+            if self.wayland_ctx.keyboard_helper.ctrl() {
+                self.wayland_ctx.commit_global_rect();
+                self.action = Action::WaitPointerPress;
+                return;
+            }
+        }
+
+        self.action = if self.wayland_ctx.config.edit {
+            Action::OnEdit(EditAction::None)
+        } else {
+            Action::Exit(ExitAction::Save)
+        };
+    }
+
+    /// 光标移动到全局坐标 global_pos，触摸时没有 pointer，不设置光标形状
+    pub(crate) fn handle_motion(
+        &mut self,
+        global_pos: (f64, f64),
+        pointer: Option<&wl_pointer::WlPointer>,
+    ) {
+        match self.action {
+            Action::OnDraw | Action::Pick(PickAction::OnDraw) => {
+                self.wayland_ctx.generate_rects_and_send_frame();
+            }
+            Action::Measure(_) => self.wayland_ctx.update_measure(),
+            Action::OnEdit(_) if self.wayland_ctx.context_menu.is_some() => {
+                if let Some(pointer) = pointer {
+                    let _ = self.wayland_ctx.set_cursor_shape(Shape::Default, pointer);
+                }
+                if self
+                    .wayland_ctx
+                    .context_menu
+                    .as_mut()
+                    .is_some_and(|m| m.hover(global_pos.0, global_pos.1))
+                {
+                    self.wayland_ctx.redraw_all();
+                }
+            }
+            Action::OnEdit(EditAction::Annotate) => self.wayland_ctx.update_annotation(),
+            Action::OnEdit(EditAction::Typing) => {}
+            Action::OnEdit(EditAction::Object) => self.wayland_ctx.update_object_drag(),
+            Action::OnEdit(_) if self.wayland_ctx.annotator.tool.is_some() => {
+                if let Some(pointer) = pointer {
+                    let _ = self.wayland_ctx.set_cursor_shape(Shape::Crosshair, pointer);
+                }
+            }
+            Action::OnEdit(edit_action) => {
+                if let (Some(global_rect), Some(pointer)) =
+                    (self.wayland_ctx.global_rect.as_ref(), pointer)
+                {
+                    // 标注在选区上层，优先显示标注的光标
+                    let hit_region_act = match self.wayland_ctx.object_at_pointer() {
+                        Some((_, handle)) if edit_action == EditAction::None => handle,
+                        _ => global_rect.hit_region(
+                            global_pos.0 as i32,
+                            global_pos.1 as i32,
+                            self.wayland_ctx.hit_threshold(),
+                        ),
+                    };
+                    let _ = self
+                        .wayland_ctx
+                        .set_cursor_shape(hit_region_act.to_cursor_shape(), pointer);
+                }
+
+                match edit_action {
+                    EditAction::None => {}
+                    _ => {
+                        if let (Some(start_pos), Some(global_rect)) = (
+                            self.wayland_ctx.pointer_helper.g_start_pos,
+                            self.wayland_ctx.global_rect.as_mut(),
+                        ) {
+                            self.action = global_rect.edit(start_pos, global_pos, self.action);
+                            if let Action::OnEdit(edge) = self.action {
+                                self.wayland_ctx.snap_global_rect(edge);
+                            }
+                            self.wayland_ctx.process_subrects_and_send();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 光标是否在当前选区内
+    fn pointer_in_selection(&self) -> bool {
+        match (
+            self.wayland_ctx.global_rect.as_ref(),
+            self.wayland_ctx.pointer_helper.g_current_pos,
+        ) {
+            (Some(rect), Some((x, y))) => rect.contains(x as i32, y as i32),
+            _ => false,
+        }
+    }
+
+    /// 滚轮：按住 Ctrl 时放大冻结画面，编辑模式下在选区内滚动时缩放选区
+    pub(crate) fn handle_scroll(&mut self, value: f64) {
+        const GROW_STEP: i32 = 10;
+
+        let Some(steps) = self.wayland_ctx.pointer_helper.scroll_steps(value) else {
+            return;
+        };
+        // 向上滚动放大
+        if self.wayland_ctx.keyboard_helper.ctrl() {
+            self.wayland_ctx.zoom_at_pointer(-steps);
+        } else if let Action::OnEdit(EditAction::None) = self.action {
+            self.wayland_ctx.grow_global_rect(-steps * GROW_STEP);
+        }
+    }
+
+    /// 操作栏显示时的快捷键：s 保存，Shift+S 另存为，c 复制，p 钉图，h 运行钩子，q 丢弃
+    pub(crate) fn action_bar_shortcut(&self, key: u32) -> Option<MenuItem> {
+        const KEY_Q: u32 = 16;
+        const KEY_P: u32 = 25;
+        const KEY_S: u32 = 31;
+        const KEY_H: u32 = 35;
+        const KEY_C: u32 = 46;
+
+        if self.action != Action::OnEdit(EditAction::None)
+            || self.wayland_ctx.action_bar().is_none()
+        {
+            return None;
+        }
+        match key {
+            KEY_S if self.wayland_ctx.keyboard_helper.shift() => Some(MenuItem::SaveAs),
+            KEY_S => Some(MenuItem::Save),
+            KEY_C => Some(MenuItem::Copy),
+            KEY_P => Some(MenuItem::Pin),
+            KEY_H => Some(MenuItem::Hook),
+            KEY_Q => Some(MenuItem::Discard),
+            _ => None,
+        }
+    }
+
+    /// 输入另存为文件名时的按键：Enter 保存，Esc 取消
+    pub(crate) fn handle_rename_key(&mut self, key: u32) {
+        const KEY_ESC: u32 = 1;
+        const KEY_BACKSPACE: u32 = 14;
+        const KEY_ENTER: u32 = 28;
+
+        match key {
+            KEY_ENTER => {
+                self.action = if self.wayland_ctx.finish_rename(true) {
+                    Action::Exit(ExitAction::Save)
+                } else {
+                    Action::OnEdit(EditAction::None)
+                };
+            }
+            KEY_ESC => {
+                self.wayland_ctx.finish_rename(false);
+                self.action = Action::OnEdit(EditAction::None);
+            }
+            KEY_BACKSPACE => self.wayland_ctx.delete_rename_char(),
+            _ => {
+                if let Some(text) = self.wayland_ctx.keyboard_helper.key_text(key) {
+                    self.wayland_ctx.insert_rename_text(&text);
+                }
+            }
+        }
+    }
+
+    /// 输入文字时的按键：Enter 结束输入，Shift+Enter 换行，Esc 结束输入
+    pub(crate) fn handle_text_key(&mut self, key: u32) {
+        const KEY_ESC: u32 = 1;
+        const KEY_BACKSPACE: u32 = 14;
+        const KEY_ENTER: u32 = 28;
+
+        match key {
+            KEY_ENTER if self.wayland_ctx.keyboard_helper.shift() => {
+                self.wayland_ctx.insert_text("\n")
+            }
+            KEY_ESC | KEY_ENTER => {
+                self.wayland_ctx.finish_text();
+                self.action = Action::OnEdit(EditAction::None);
+            }
+            KEY_BACKSPACE => self.wayland_ctx.delete_text_char(),
+            _ => {
+                if let Some(text) = self.wayland_ctx.keyboard_helper.key_text(key) {
+                    self.wayland_ctx.insert_text(&text);
+                }
+            }
+        }
+    }
+}
@@ -1,8 +1,13 @@
+mod input;
+mod pin;
+mod wl_data_device;
+mod wl_touch;
 mod wp_cursor_shape_manager_v1;
 mod wp_viewporter;
 mod xdg;
 mod zwlr_layer_shell_v1;
 mod zwlr_screencopy_manager_v1;
+mod zwp_text_input_v3;
 
 use log::*;
 use smithay_client_toolkit::{
@@ -14,7 +19,8 @@ use wayland_client::{
     globals::GlobalListContents,
     protocol::{
         wl_callback::{self},
-        wl_compositor, wl_keyboard, wl_output, wl_pointer, wl_registry, wl_seat, wl_surface,
+        wl_compositor, wl_data_device_manager, wl_keyboard, wl_output, wl_pointer, wl_registry,
+        wl_seat, wl_surface,
     },
 };
 use wayland_protocols::{
@@ -22,6 +28,7 @@ use wayland_protocols::{
         cursor_shape::v1::client::{
             wp_cursor_shape_device_v1::Shape, wp_cursor_shape_manager_v1::WpCursorShapeManagerV1,
         },
+        text_input::zv3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3,
         viewporter::client::wp_viewporter::WpViewporter,
     },
     xdg::{shell::client::xdg_wm_base, xdg_output::zv1::client::zxdg_output_manager_v1},
@@ -32,9 +39,10 @@ use wayland_protocols_wlr::{
 };
 
 use crate::{
-    action::{Action, EditAction, IsFreeze},
+    action::{Action, EditAction, ExitAction, IsFreeze, MeasureAction, PickAction},
     foam_outputs,
     foamshot::FoamShot,
+    result::Status,
     zwlr_screencopy_mode::ZwlrScreencopyMode,
 };
 
@@ -71,8 +79,10 @@ impl Dispatch<wl_registry::WlRegistry, ()> for FoamShot {
                             let pointer = seat.get_pointer(qh, ());
                             let keyboard = seat.get_keyboard(qh, ());
                             app.wayland_ctx.pointer_helper.pointer = Some(pointer);
-                            app.wayland_ctx.keyboard = Some(keyboard);
+                            app.wayland_ctx.keyboard_helper.keyboard = Some(keyboard);
                             app.wayland_ctx.seat = Some((seat, name));
+                            app.wayland_ctx.init_text_input();
+                            app.wayland_ctx.init_data_device();
                         }
                     }
                     // 动态管理 outputs
@@ -141,6 +151,24 @@ impl Dispatch<wl_registry::WlRegistry, ()> for FoamShot {
                             app.wayland_ctx.viewporter = Some((viewporter, name));
                         }
                     }
+                    // 输入法
+                    _ if interface_name == ZwpTextInputManagerV3::interface().name => {
+                        if app.wayland_ctx.text_input_helper.manager.is_none() {
+                            let manager = proxy.bind(name, version, qh, ());
+                            app.wayland_ctx.text_input_helper.manager = Some((manager, name));
+                            app.wayland_ctx.init_text_input();
+                        }
+                    }
+                    // 拖放
+                    _ if interface_name
+                        == wl_data_device_manager::WlDataDeviceManager::interface().name =>
+                    {
+                        if app.wayland_ctx.drag_helper.manager.is_none() {
+                            let manager = proxy.bind(name, version.min(3), qh, ());
+                            app.wayland_ctx.drag_helper.manager = Some((manager, name));
+                            app.wayland_ctx.init_data_device();
+                        }
+                    }
                     _ => (),
                 }
             }
@@ -195,6 +223,10 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
         conn: &wayland_client::Connection,
         qh: &wayland_client::QueueHandle<Self>,
     ) {
+        if app.wayland_ctx.pin.is_some() {
+            app.handle_pin_pointer(proxy, event);
+            return;
+        }
         match event {
             wl_pointer::Event::Leave { serial, surface } => {
                 let _ = app.wayland_ctx.set_cursor_shape(Shape::Default, proxy);
@@ -211,7 +243,7 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                     Some(idx) => *idx,
                     None => {
                         error!("can not get surface index, exit!");
-                        std::process::exit(0)
+                        app.finish(Status::Error, Some("can not get surface index".to_string()))
                     }
                 };
                 app.wayland_ctx.unknown_index = Some(surface_index);
@@ -230,10 +262,11 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                     Some(output) => output,
                     None => {
                         error!("can not get foam_output, exit!");
-                        std::process::exit(0)
+                        app.finish(Status::Error, Some("can not get foam_output".to_string()))
                     }
                 };
 
+                let (surface_x, surface_y) = foam_output.unzoom_pos(surface_x, surface_y);
                 let x = surface_x + foam_output.global_x as f64;
                 let y = surface_y + foam_output.global_y as f64;
 
@@ -248,7 +281,7 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                     // NOTE:  full screen mode handle
                     if app.wayland_ctx.config.full_screen {
                         app.wayland_ctx.set_one_max(surface_index);
-                        app.action = Action::Exit;
+                        app.action = Action::Exit(ExitAction::Save);
                         return;
                     }
 
@@ -276,56 +309,12 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                 state,
             } => {
                 if let Ok(button_state) = state.into_result() {
+                    if button_state == wl_pointer::ButtonState::Pressed {
+                        app.wayland_ctx.pointer_helper.button_serial = serial;
+                    }
                     match button_state {
-                        wl_pointer::ButtonState::Pressed => {
-                            app.wayland_ctx.pointer_helper.is_pressing = true;
-                            match app.action {
-                                Action::WaitPointerPress => {
-                                    app.wayland_ctx.pointer_helper.start_index =
-                                        app.wayland_ctx.current_index;
-                                    app.wayland_ctx.pointer_helper.g_start_pos =
-                                        app.wayland_ctx.pointer_helper.g_current_pos;
-                                    app.wayland_ctx.generate_rects_and_send_frame();
-                                    app.action = Action::OnDraw;
-                                }
-                                Action::OnEdit(EditAction::None) => {
-                                    app.wayland_ctx.pointer_helper.g_start_pos =
-                                        app.wayland_ctx.pointer_helper.g_current_pos;
-
-                                    if let Some(current_pos) =
-                                        app.wayland_ctx.pointer_helper.g_current_pos
-                                    {
-                                        if let Some(global_rect) =
-                                            app.wayland_ctx.global_rect.as_ref()
-                                        {
-                                            let hit_region = global_rect.hit_region(
-                                                current_pos.0 as i32,
-                                                current_pos.1 as i32,
-                                                15,
-                                            );
-                                            app.action = Action::OnEdit(hit_region);
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                        wl_pointer::ButtonState::Released => {
-                            app.wayland_ctx.pointer_helper.is_pressing = false;
-
-                            if app.action == Action::OnDraw {
-                                app.wayland_ctx.pointer_helper.end_index =
-                                    app.wayland_ctx.current_index;
-                                app.wayland_ctx.pointer_helper.g_end_pos =
-                                    app.wayland_ctx.pointer_helper.g_current_pos;
-                            }
-
-                            app.action = if app.wayland_ctx.config.edit {
-                                Action::OnEdit(EditAction::None)
-                            } else {
-                                Action::Exit
-                            };
-                        }
+                        wl_pointer::ButtonState::Pressed => app.handle_press(button),
+                        wl_pointer::ButtonState::Released => app.handle_release(button),
                         _ => (),
                     }
                 }
@@ -343,7 +332,7 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                     (Some(u), Some(s)) => (u, s),
                     _ => {
                         error!("can not get surface index, exit!");
-                        std::process::exit(0)
+                        app.finish(Status::Error, Some("can not get surface index".to_string()))
                     }
                 };
 
@@ -351,7 +340,7 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                     Some(o) => o,
                     None => {
                         error!("can not get foam_outputs, exit!");
-                        std::process::exit(0)
+                        app.finish(Status::Error, Some("can not get foam_outputs".to_string()))
                     }
                 };
 
@@ -361,6 +350,8 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                         _ => return,
                     };
 
+                // 画面放大时换算回真实坐标
+                let (surface_x, surface_y) = unknown_output.unzoom_pos(surface_x, surface_y);
                 let (x, y) = foam_outputs::FoamOutput::convert_pos_to_surface(
                     unknown_output,
                     start_output,
@@ -374,41 +365,14 @@ impl Dispatch<wl_pointer::WlPointer, ()> for FoamShot {
                 );
                 app.wayland_ctx.pointer_helper.g_current_pos = Some(global_pos);
 
-                match app.action {
-                    Action::OnDraw => {
-                        app.wayland_ctx.generate_rects_and_send_frame();
-                    }
-                    Action::OnEdit(edit_action) => {
-                        if let Some(global_rect) = app.wayland_ctx.global_rect.as_ref() {
-                            let hit_region_act = global_rect.hit_region(
-                                global_pos.0 as i32,
-                                global_pos.1 as i32,
-                                15,
-                            );
-                            let _ = app
-                                .wayland_ctx
-                                .set_cursor_shape(hit_region_act.to_cursor_shape(), proxy);
-                        }
-
-                        match edit_action {
-                            EditAction::None => {
-                                return;
-                            }
-                            _ => {
-                                if let (Some(start_pos), Some(global_rect)) = (
-                                    app.wayland_ctx.pointer_helper.g_start_pos,
-                                    app.wayland_ctx.global_rect.as_mut(),
-                                ) {
-                                    app.action =
-                                        global_rect.edit(start_pos, global_pos, app.action);
-                                    app.wayland_ctx.process_subrects_and_send();
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+                app.handle_motion(global_pos, Some(proxy));
             }
+
+            wl_pointer::Event::Axis {
+                axis: wayland_client::WEnum::Value(wl_pointer::Axis::VerticalScroll),
+                value,
+                ..
+            } => app.handle_scroll(value),
             _ => (),
         }
     }
@@ -429,6 +393,41 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for FoamShot {
         const KEY_ESC: u32 = 1;
         const KEY_A: u32 = 30;
         const KEY_S: u32 = 31;
+        const KEY_C: u32 = 46;
+        const KEY_R: u32 = 19;
+        const KEY_ENTER: u32 = 28;
+        const KEY_Z: u32 = 44;
+        const KEY_BACKSPACE: u32 = 14;
+        const KEY_DELETE: u32 = 111;
+
+        match event {
+            wl_keyboard::Event::Keymap {
+                format: wayland_client::WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+                fd,
+                size,
+            } => {
+                if let Err(e) = app.wayland_ctx.keyboard_helper.set_keymap(fd, size) {
+                    warn!("load keymap error: {}", e);
+                }
+                return;
+            }
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                app.wayland_ctx.keyboard_helper.update_modifiers(
+                    mods_depressed,
+                    mods_latched,
+                    mods_locked,
+                    group,
+                );
+                return;
+            }
+            _ => {}
+        }
 
         // 使用模式匹配替代多重if嵌套
         if let wl_keyboard::Event::Key {
@@ -439,24 +438,74 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for FoamShot {
         {
             debug!("Key pressed: {}", key);
 
+            // 钉图时只响应 Esc 关闭
+            if app.wayland_ctx.pin.is_some() {
+                if key == KEY_ESC {
+                    app.finish(Status::Pinned, None)
+                }
+                return;
+            }
+
+            if let Some(item) = app.action_bar_shortcut(key) {
+                app.run_menu_item(item);
+                return;
+            }
+
             match key {
+                // 输入文字时按键都用于编辑文字
+                _ if matches!(app.action, Action::OnEdit(EditAction::Typing)) => {
+                    app.handle_text_key(key)
+                }
+                _ if matches!(app.action, Action::OnEdit(EditAction::Rename)) => {
+                    app.handle_rename_key(key)
+                }
+                // 测量模式只响应退出和撤销
+                _ if matches!(app.action, Action::Measure(_)) => match key {
+                    KEY_ESC => app.finish(Status::Cancelled, None),
+                    KEY_Z if app.wayland_ctx.keyboard_helper.ctrl() => {
+                        app.wayland_ctx.undo_measure();
+                        app.action = Action::Measure(MeasureAction::WaitPress);
+                    }
+                    _ => {}
+                },
                 KEY_A => {
                     let current_output = app.wayland_ctx.current_index;
                     app.wayland_ctx.set_one_max(current_output.unwrap());
-                    app.action = Action::Exit
+                    app.action = Action::Exit(ExitAction::Save)
+                }
+                KEY_C => match app.action {
+                    Action::WaitPointerPress => app.action = Action::Pick(PickAction::WaitPress),
+                    Action::Pick(PickAction::WaitPress) => app.action = Action::WaitPointerPress,
+                    _ => {}
+                },
+                KEY_R
+                    if matches!(
+                        app.action,
+                        Action::WaitPointerPress | Action::OnEdit(EditAction::None)
+                    ) && app.restore_last_region() =>
+                {
+                    // 恢复的选区始终进入编辑模式，按 s 保存
+                    app.wayland_ctx.config.edit = true;
+                    app.action = Action::OnEdit(EditAction::None);
                 }
                 KEY_S => match app.action {
-                    Action::WaitPointerPress => {
+                    // 已有提交的选区时可以直接保存
+                    Action::WaitPointerPress if !app.wayland_ctx.committed_rects.is_empty() => {
+                        app.action = Action::Exit(ExitAction::Save)
+                    }
+                    Action::WaitPointerPress | Action::Pick(_) => {
                         return;
                     }
                     Action::Init => {
                         return;
                     }
-                    Action::Exit => {
+                    Action::Exit(_) => {
                         return;
                     }
-                    _ => app.action = Action::Exit,
+                    _ => app.action = Action::Exit(ExitAction::Save),
                 },
+                // 重新编辑项目时画面来自项目文件，不能重新截取
+                KEY_F if app.wayland_ctx.config.project_input.is_some() => {}
                 KEY_F => {
                     app.wayland_ctx.current_freeze = !app.wayland_ctx.current_freeze;
                     app.action = if app.wayland_ctx.current_freeze {
@@ -465,6 +514,32 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for FoamShot {
                         Action::ToggleFreeze(IsFreeze::UnFreeze)
                     };
                 }
+                // Ctrl+Z 撤销，Ctrl+Shift+Z 重做
+                KEY_Z if app.wayland_ctx.keyboard_helper.ctrl() => {
+                    if let Action::OnEdit(EditAction::None) = app.action {
+                        let redo = app.wayland_ctx.keyboard_helper.shift();
+                        app.wayland_ctx.undo_edit(redo);
+                    }
+                }
+                // 删除选中的标注
+                KEY_DELETE | KEY_BACKSPACE => {
+                    if let Action::OnEdit(EditAction::None) = app.action {
+                        app.wayland_ctx.delete_selected_object();
+                    }
+                }
+                // 提交当前选区，继续添加下一个
+                KEY_ENTER => {
+                    if let Action::OnEdit(_) = app.action {
+                        app.wayland_ctx.commit_global_rect();
+                        app.action = Action::WaitPointerPress;
+                    }
+                }
+                KEY_ESC if app.wayland_ctx.context_menu.is_some() => {
+                    app.wayland_ctx.close_context_menu();
+                }
+                KEY_ESC if app.wayland_ctx.annotator.selected.is_some() => {
+                    app.wayland_ctx.deselect_object();
+                }
                 KEY_ESC => match app.action {
                     Action::OnEdit(a) => {
                         app.action = if app.wayland_ctx.current_freeze {
@@ -473,9 +548,7 @@ impl Dispatch<wl_keyboard::WlKeyboard, ()> for FoamShot {
                             Action::ToggleFreeze(IsFreeze::UnFreeze)
                         };
                     }
-                    _ => {
-                        std::process::exit(0);
-                    }
+                    _ => app.finish(Status::Cancelled, None),
                 },
                 _ => {}
             }
@@ -602,7 +675,6 @@ impl Dispatch<wl_compositor::WlCompositor, ()> for FoamShot {
     ) {
     }
 }
-// NOTE: unused
 #[allow(unused_variables)]
 impl Dispatch<wl_seat::WlSeat, ()> for FoamShot {
     fn event(
@@ -613,6 +685,15 @@ impl Dispatch<wl_seat::WlSeat, ()> for FoamShot {
         conn: &wayland_client::Connection,
         qh: &wayland_client::QueueHandle<Self>,
     ) {
+        // 只有支持触摸的 seat 才能请求 wl_touch
+        if let wl_seat::Event::Capabilities {
+            capabilities: wayland_client::WEnum::Value(capabilities),
+        } = event
+            && capabilities.contains(wl_seat::Capability::Touch)
+            && app.wayland_ctx.touch_helper.touch.is_none()
+        {
+            app.wayland_ctx.touch_helper.touch = Some(proxy.get_touch(qh, ()));
+        }
     }
 }
 
@@ -628,11 +709,14 @@ impl Dispatch<wl_callback::WlCallback, usize> for FoamShot {
     ) {
         if let wl_callback::Event::Done { callback_data } = event {
             match app.action {
-                Action::OnDraw => {
-                    app.wayland_ctx.update_select_region();
+                Action::WaitPointerPress
+                | Action::OnDraw
+                | Action::Pick(_)
+                | Action::Measure(_) => {
+                    app.wayland_ctx.update_select_region(false);
                 }
                 Action::OnEdit(a) => {
-                    app.wayland_ctx.update_select_region();
+                    app.wayland_ctx.update_select_region(true);
                 }
                 _ => {}
             }
@@ -0,0 +1,133 @@
+use log::{debug, error};
+use wayland_client::{Dispatch, Proxy, protocol::wl_pointer, protocol::wl_surface};
+use wayland_protocols::wp::cursor_shape::v1::client::wp_cursor_shape_device_v1::Shape;
+use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_surface_v1;
+
+use crate::foamshot::FoamShot;
+use crate::pin::PinSurface;
+use crate::pointer_helper::{BTN_LEFT, BTN_RIGHT};
+use crate::result::Status;
+
+impl FoamShot {
+    /// 钉图模式下的指针事件：左键拖动，双击或右键关闭，滚轮缩放，Ctrl+滚轮调整不透明度
+    pub(crate) fn handle_pin_pointer(
+        &mut self,
+        proxy: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+    ) {
+        match event {
+            wl_pointer::Event::Enter {
+                serial,
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                self.wayland_ctx.pointer_helper.serial = serial;
+                if let Some(pin) = self.wayland_ctx.pin.as_mut() {
+                    pin.motion((surface_x, surface_y));
+                }
+                let _ = self.wayland_ctx.set_cursor_shape(Shape::Grab, proxy);
+            }
+            wl_pointer::Event::Button {
+                serial,
+                time,
+                button,
+                state: wayland_client::WEnum::Value(state),
+                ..
+            } => {
+                // 按住 Shift 拖动时把截图拖放到其他程序
+                if (button, state) == (BTN_LEFT, wl_pointer::ButtonState::Pressed)
+                    && self.wayland_ctx.keyboard_helper.shift()
+                {
+                    self.wayland_ctx.pointer_helper.button_serial = serial;
+                    self.start_drag();
+                    return;
+                }
+                let Some(pin) = self.wayland_ctx.pin.as_mut() else {
+                    return;
+                };
+                match (button, state) {
+                    (BTN_LEFT, wl_pointer::ButtonState::Pressed) => {
+                        if pin.press(time) {
+                            debug!("pin closed by double click");
+                            self.finish(Status::Pinned, None)
+                        }
+                        let _ = self.wayland_ctx.set_cursor_shape(Shape::Grabbing, proxy);
+                    }
+                    (BTN_LEFT, wl_pointer::ButtonState::Released) => {
+                        pin.release();
+                        let _ = self.wayland_ctx.set_cursor_shape(Shape::Grab, proxy);
+                    }
+                    (BTN_RIGHT, wl_pointer::ButtonState::Pressed) => {
+                        self.finish(Status::Pinned, None)
+                    }
+                    _ => {}
+                }
+            }
+            wl_pointer::Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                if let Some(pin) = self.wayland_ctx.pin.as_mut() {
+                    pin.motion((surface_x, surface_y));
+                }
+            }
+            wl_pointer::Event::Axis {
+                axis: wayland_client::WEnum::Value(wl_pointer::Axis::VerticalScroll),
+                value,
+                ..
+            } => {
+                let Some(steps) = self.wayland_ctx.pointer_helper.scroll_steps(value) else {
+                    return;
+                };
+                let ctrl = self.wayland_ctx.keyboard_helper.ctrl();
+                if let Some(pin) = self.wayland_ctx.pin.as_mut() {
+                    if ctrl {
+                        pin.fade_by(steps);
+                    } else {
+                        pin.zoom_by(steps);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, PinSurface> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &zwlr_layer_surface_v1::ZwlrLayerSurfaceV1,
+        event: <zwlr_layer_surface_v1::ZwlrLayerSurfaceV1 as Proxy>::Event,
+        _data: &PinSurface,
+        _conn: &wayland_client::Connection,
+        _qh: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                proxy.ack_configure(serial);
+                if let Some(pin) = app.wayland_ctx.pin.as_mut() {
+                    pin.configure();
+                }
+            }
+            zwlr_layer_surface_v1::Event::Closed => {
+                error!("pin surface closed by compositor");
+                app.finish(Status::Pinned, None)
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<wl_surface::WlSurface, PinSurface> for FoamShot {
+    fn event(
+        _app: &mut Self,
+        _proxy: &wl_surface::WlSurface,
+        _event: <wl_surface::WlSurface as Proxy>::Event,
+        _data: &PinSurface,
+        _conn: &wayland_client::Connection,
+        _qh: &wayland_client::QueueHandle<Self>,
+    ) {
+    }
+}
@@ -0,0 +1,135 @@
+use log::{debug, error};
+use wayland_client::{
+    Dispatch, Proxy, event_created_child,
+    protocol::{wl_data_device, wl_data_device_manager, wl_data_offer, wl_data_source, wl_surface},
+};
+
+use crate::action::{Action, EditAction};
+use crate::drag_helper::DragIconSurface;
+use crate::foamshot::FoamShot;
+use crate::result::Status;
+
+impl FoamShot {
+    /// 开始拖放截图，失败时发送通知
+    pub(crate) fn start_drag(&mut self) {
+        if self.wayland_ctx.drag_helper.is_dragging() {
+            return;
+        }
+        if let Err(e) = self.wayland_ctx.start_drag() {
+            error!("start drag error: {}", e);
+            self.wayland_ctx.drag_helper.finish();
+            self.send_error("can not drag the capture");
+        }
+    }
+
+    /// 拖放完成，编辑模式下遮罩层已经隐藏，直接退出
+    pub(crate) fn finish_drag(&mut self) {
+        self.wayland_ctx.drag_helper.finish();
+        self.wayland_ctx.drag_helper.created_file = None;
+        if self.wayland_ctx.pin.is_none() {
+            self.wayland_ctx.remember_global_rect();
+            self.finish(Status::Saved, None)
+        }
+    }
+
+    /// 拖放取消，回到编辑模式继续操作
+    pub(crate) fn cancel_drag(&mut self) {
+        self.wayland_ctx.drag_helper.finish();
+        self.wayland_ctx.cancel_drag();
+        if self.wayland_ctx.pin.is_none() {
+            self.action = Action::OnEdit(EditAction::None);
+        }
+    }
+}
+
+// NOTE: unused
+#[allow(unused_variables)]
+impl Dispatch<wl_data_device_manager::WlDataDeviceManager, ()> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &wl_data_device_manager::WlDataDeviceManager,
+        event: <wl_data_device_manager::WlDataDeviceManager as Proxy>::Event,
+        data: &(),
+        conn: &wayland_client::Connection,
+        qh: &wayland_client::QueueHandle<Self>,
+    ) {
+    }
+}
+
+/// 只作为拖放源，其他程序的剪贴板和拖放内容直接丢弃
+impl Dispatch<wl_data_device::WlDataDevice, ()> for FoamShot {
+    fn event(
+        _app: &mut Self,
+        _proxy: &wl_data_device::WlDataDevice,
+        event: <wl_data_device::WlDataDevice as Proxy>::Event,
+        _data: &(),
+        _conn: &wayland_client::Connection,
+        _qh: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_device::Event::Selection { id: Some(offer) }
+            | wl_data_device::Event::Enter {
+                id: Some(offer), ..
+            } => offer.destroy(),
+            _ => (),
+        }
+    }
+
+    event_created_child!(FoamShot, wl_data_device::WlDataDevice, [
+        wl_data_device::EVT_DATA_OFFER_OPCODE => (wl_data_offer::WlDataOffer, ()),
+    ]);
+}
+
+// NOTE: unused
+#[allow(unused_variables)]
+impl Dispatch<wl_data_offer::WlDataOffer, ()> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &wl_data_offer::WlDataOffer,
+        event: <wl_data_offer::WlDataOffer as Proxy>::Event,
+        data: &(),
+        conn: &wayland_client::Connection,
+        qh: &wayland_client::QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_data_source::WlDataSource, ()> for FoamShot {
+    fn event(
+        app: &mut Self,
+        _proxy: &wl_data_source::WlDataSource,
+        event: <wl_data_source::WlDataSource as Proxy>::Event,
+        _data: &(),
+        _conn: &wayland_client::Connection,
+        _qh: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_source::Event::Send { mime_type, fd } => {
+                debug!("drag target requests {}", mime_type);
+                app.wayland_ctx.drag_helper.send(&mime_type, fd);
+            }
+            wl_data_source::Event::DndFinished => {
+                debug!("drag finished");
+                app.finish_drag();
+            }
+            wl_data_source::Event::Cancelled => {
+                debug!("drag cancelled");
+                app.cancel_drag();
+            }
+            _ => (),
+        }
+    }
+}
+
+#[allow(unused_variables)]
+impl Dispatch<wl_surface::WlSurface, DragIconSurface> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &wl_surface::WlSurface,
+        event: <wl_surface::WlSurface as Proxy>::Event,
+        data: &DragIconSurface,
+        _conn: &wayland_client::Connection,
+        _qh: &wayland_client::QueueHandle<Self>,
+    ) {
+    }
+}
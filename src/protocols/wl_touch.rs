@@ -0,0 +1,133 @@
+use wayland_client::{Dispatch, Proxy, protocol::wl_touch};
+
+use crate::{
+    action::{Action, EditAction},
+    foamshot::FoamShot,
+    pointer_helper::BTN_LEFT,
+    touch_helper::Pinch,
+};
+
+impl FoamShot {
+    /// 将触点在 surface 上的坐标转换为全局坐标
+    fn touch_global_pos(&self, surface_index: usize, x: f64, y: f64) -> Option<(f64, f64)> {
+        let output = self.wayland_ctx.foam_outputs.as_ref()?.get(surface_index)?;
+        let (x, y) = output.unzoom_pos(x, y);
+        Some((output.global_x as f64 + x, output.global_y as f64 + y))
+    }
+
+    /// 第二个触点按下时，在编辑模式下开始双指缩放
+    fn begin_pinch(&mut self) {
+        let Action::OnEdit(_) = self.action else {
+            return;
+        };
+        let (Some(start_distance), Some(start_rect)) = (
+            self.wayland_ctx.touch_helper.distance(),
+            self.wayland_ctx.global_rect.clone(),
+        ) else {
+            return;
+        };
+        if self.wayland_ctx.edit_snapshot.is_none() {
+            self.wayland_ctx.begin_edit_gesture();
+        }
+        self.action = Action::OnEdit(EditAction::None);
+        self.wayland_ctx.touch_helper.pinch = Some(Pinch {
+            start_distance,
+            start_rect,
+        });
+    }
+
+    fn update_pinch(&mut self) {
+        let touch_helper = &self.wayland_ctx.touch_helper;
+        let (Some(pinch), Some(distance)) = (touch_helper.pinch.as_ref(), touch_helper.distance())
+        else {
+            return;
+        };
+        if pinch.start_distance <= 0.0 {
+            return;
+        }
+        let factor = distance / pinch.start_distance;
+        let mut rect = pinch.start_rect.clone();
+        rect.scale_around_center(&pinch.start_rect, factor);
+        self.wayland_ctx.global_rect = Some(rect);
+        self.wayland_ctx.process_subrects_and_send();
+    }
+}
+
+#[allow(unused_variables)]
+impl Dispatch<wl_touch::WlTouch, ()> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &wl_touch::WlTouch,
+        event: <wl_touch::WlTouch as Proxy>::Event,
+        data: &(),
+        conn: &wayland_client::Connection,
+        qh: &wayland_client::QueueHandle<Self>,
+    ) {
+        match event {
+            wl_touch::Event::Down {
+                surface, id, x, y, ..
+            } => {
+                let Some(surface_index) = surface.data::<usize>().copied() else {
+                    return;
+                };
+                let Some(global_pos) = app.touch_global_pos(surface_index, x, y) else {
+                    return;
+                };
+                let touch_helper = &mut app.wayland_ctx.touch_helper;
+                touch_helper.points.insert(id, global_pos);
+                touch_helper.surfaces.insert(id, surface_index);
+
+                if touch_helper.primary.is_none() {
+                    // 第一个触点等同于鼠标移动到该位置后按下左键
+                    touch_helper.primary = Some(id);
+                    app.wayland_ctx.current_index = Some(surface_index);
+                    app.wayland_ctx.unknown_index = Some(surface_index);
+                    app.wayland_ctx.pointer_helper.start_index = Some(surface_index);
+                    app.wayland_ctx.pointer_helper.g_current_pos = Some(global_pos);
+                    app.handle_press(BTN_LEFT);
+                } else if touch_helper.points.len() == 2 {
+                    app.begin_pinch();
+                }
+            }
+            wl_touch::Event::Motion { id, x, y, .. } => {
+                let Some(surface_index) = app.wayland_ctx.touch_helper.surfaces.get(&id).copied()
+                else {
+                    return;
+                };
+                let Some(global_pos) = app.touch_global_pos(surface_index, x, y) else {
+                    return;
+                };
+                app.wayland_ctx.touch_helper.points.insert(id, global_pos);
+
+                if app.wayland_ctx.touch_helper.pinch.is_some() {
+                    app.update_pinch();
+                } else if app.wayland_ctx.touch_helper.primary == Some(id) {
+                    app.wayland_ctx.pointer_helper.g_current_pos = Some(global_pos);
+                    app.handle_motion(global_pos, None);
+                }
+            }
+            wl_touch::Event::Up { id, .. } => {
+                let touch_helper = &mut app.wayland_ctx.touch_helper;
+                touch_helper.points.remove(&id);
+                touch_helper.surfaces.remove(&id);
+
+                if touch_helper.pinch.is_some() {
+                    // 缩放结束后所有手指抬起才结束本次操作，不触发松开
+                    if touch_helper.points.is_empty() {
+                        touch_helper.reset();
+                        app.wayland_ctx.end_edit_gesture();
+                        app.wayland_ctx.pointer_helper.reset_press();
+                    }
+                } else if touch_helper.primary == Some(id) {
+                    touch_helper.primary = None;
+                    app.handle_release(BTN_LEFT);
+                }
+            }
+            wl_touch::Event::Cancel => {
+                app.wayland_ctx.touch_helper.reset();
+                app.wayland_ctx.pointer_helper.reset_press();
+            }
+            _ => (),
+        }
+    }
+}
@@ -4,6 +4,7 @@ use wayland_protocols::xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_ba
 use wayland_protocols::xdg::xdg_output::zv1::client::{zxdg_output_manager_v1, zxdg_output_v1};
 
 use crate::foamshot::FoamShot;
+use crate::result::Status;
 
 // NOTE: unused
 #[allow(unused_variables)]
@@ -110,7 +111,7 @@ impl Dispatch<xdg_toplevel::XdgToplevel, ()> for FoamShot {
         qh: &wayland_client::QueueHandle<Self>,
     ) {
         match event {
-            xdg_toplevel::Event::Close => std::process::exit(0),
+            xdg_toplevel::Event::Close => app.finish(Status::Cancelled, None),
             xdg_toplevel::Event::WmCapabilities { capabilities } => {
                 // info!("Capabilities: {:?}", capabilities);
             }
@@ -2,7 +2,7 @@ use log::debug;
 use wayland_client::{Dispatch, Proxy};
 use wayland_protocols_wlr::layer_shell::v1::client::{zwlr_layer_shell_v1, zwlr_layer_surface_v1};
 
-use crate::action::Action;
+use crate::action::{Action, EditAction, ExitAction};
 use crate::foamshot::FoamShot;
 
 impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, usize> for FoamShot {
@@ -34,10 +34,31 @@ impl Dispatch<zwlr_layer_surface_v1::ZwlrLayerSurfaceV1, usize> for FoamShot {
                         == app.wayland_ctx.foam_outputs.as_ref().unwrap().len()
                     {
                         app.wayland_ctx.current_freeze = app.wayland_ctx.config.freeze;
-                        app.action = Action::WaitPointerPress;
+                        app.action = app.idle_action();
+                        if app.wayland_ctx.config.last_region && app.restore_last_region() {
+                            app.action = Action::Exit(ExitAction::Save);
+                        }
+                        // 项目文件中有选区时直接进入编辑模式
+                        if app.wayland_ctx.config.project_input.is_some()
+                            && app.wayland_ctx.global_rect.is_some()
+                        {
+                            app.wayland_ctx.process_subrects_and_send();
+                            app.action = Action::OnEdit(EditAction::None);
+                        }
 
                         app.wayland_ctx.layer_ready = 0;
                     }
+                } else if let Some(output) = app
+                    .wayland_ctx
+                    .foam_outputs
+                    .as_mut()
+                    .and_then(|outputs| outputs.get_mut(*data))
+                    .filter(|output| output.hidden)
+                {
+                    // 拖放取消后重新显示遮罩层
+                    output.hidden = false;
+                    app.wayland_ctx.attach_with_udata(*data);
+                    app.wayland_ctx.redraw_all();
                 }
             }
             zwlr_layer_surface_v1::Event::Closed => {
@@ -5,7 +5,10 @@ use wayland_protocols_wlr::screencopy::v1::client::{
     zwlr_screencopy_frame_v1, zwlr_screencopy_manager_v1,
 };
 
-use crate::{action::Action, foamshot::FoamShot};
+use crate::{
+    action::{Action, ExitAction},
+    foamshot::FoamShot,
+};
 
 impl Dispatch<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1, usize> for FoamShot {
     fn event(
@@ -75,7 +78,7 @@ impl Dispatch<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1, usize> for FoamSh
             }
             zwlr_screencopy_frame_v1::Event::Failed => {
                 warn!("buffer copy error");
-                app.action = Action::Exit;
+                app.action = Action::Exit(ExitAction::Save);
             }
             _ => (),
         }
@@ -0,0 +1,57 @@
+use wayland_client::{Dispatch, Proxy};
+use wayland_protocols::wp::text_input::zv3::client::{
+    zwp_text_input_manager_v3, zwp_text_input_v3,
+};
+
+use crate::{
+    action::{Action, EditAction},
+    foamshot::FoamShot,
+};
+
+// NOTE: unused
+#[allow(unused_variables)]
+impl Dispatch<zwp_text_input_manager_v3::ZwpTextInputManagerV3, ()> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+        event: <zwp_text_input_manager_v3::ZwpTextInputManagerV3 as Proxy>::Event,
+        data: &(),
+        conn: &wayland_client::Connection,
+        qh: &wayland_client::QueueHandle<Self>,
+    ) {
+    }
+}
+
+#[allow(unused_variables)]
+impl Dispatch<zwp_text_input_v3::ZwpTextInputV3, ()> for FoamShot {
+    fn event(
+        app: &mut Self,
+        proxy: &zwp_text_input_v3::ZwpTextInputV3,
+        event: <zwp_text_input_v3::ZwpTextInputV3 as Proxy>::Event,
+        data: &(),
+        conn: &wayland_client::Connection,
+        qh: &wayland_client::QueueHandle<Self>,
+    ) {
+        let helper = &mut app.wayland_ctx.text_input_helper;
+        match event {
+            zwp_text_input_v3::Event::Enter { surface } => {
+                helper.focused = surface.data::<usize>().copied();
+                // 正在输入时获得焦点，立即启用输入法
+                if let Action::OnEdit(EditAction::Typing) = app.action {
+                    app.wayland_ctx.enable_text_input();
+                }
+            }
+            zwp_text_input_v3::Event::Leave { surface } => helper.leave(),
+            zwp_text_input_v3::Event::PreeditString { text, .. } => {
+                helper.pending_preedit = text;
+            }
+            zwp_text_input_v3::Event::CommitString { text } => {
+                helper.pending_commit = text;
+            }
+            zwp_text_input_v3::Event::Done { serial } => {
+                app.wayland_ctx.apply_text_input(serial);
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use cairo::{Context, ImageSurface};
+
+/// 遮挡方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// 马赛克
+    Pixelate,
+    /// 高斯模糊
+    Blur,
+}
+
+/// 将用户坐标下的矩形转换为目标 surface 上的像素矩形
+fn device_rect(cr: &Context, rect: (f64, f64, f64, f64)) -> Option<(i32, i32, i32, i32)> {
+    let (sx, sy, ex, ey) = rect;
+    let (x0, y0) = cr.user_to_device(sx.min(ex), sy.min(ey));
+    let (x1, y1) = cr.user_to_device(sx.max(ex), sy.max(ey));
+    let (x, y) = (x0.floor() as i32, y0.floor() as i32);
+    let (w, h) = (x1.ceil() as i32 - x, y1.ceil() as i32 - y);
+    (w > 0 && h > 0).then_some((x, y, w, h))
+}
+
+/// 对 cr 目标 surface 上 rect 覆盖的像素进行模糊或马赛克，rect 为用户坐标，size 为用户坐标下的强度
+///
+/// 直接替换目标上的像素，保存后无法还原
+pub fn apply(cr: &Context, rect: (f64, f64, f64, f64), redaction: Redaction, size: f64) {
+    let Some((x, y, w, h)) = device_rect(cr, rect) else {
+        return;
+    };
+    // 强度随缩放变化，保证预览与保存结果一致
+    let (scale, _) = cr.user_to_device_distance(1.0, 0.0).unwrap_or((1.0, 0.0));
+    let size = ((size * scale.abs()).round() as usize).max(1);
+
+    let Ok(mut region) = ImageSurface::create(cairo::Format::ARgb32, w, h) else {
+        return;
+    };
+    {
+        let Ok(copy) = Context::new(&region) else {
+            return;
+        };
+        if copy
+            .set_source_surface(cr.target(), -x as f64, -y as f64)
+            .is_err()
+            || copy.paint().is_err()
+        {
+            return;
+        }
+    }
+
+    let stride = region.stride() as usize;
+    {
+        let Ok(mut data) = region.data() else {
+            return;
+        };
+        match redaction {
+            Redaction::Pixelate => pixelate(&mut data, w as usize, h as usize, stride, size),
+            Redaction::Blur => {
+                // 三次盒式模糊近似高斯模糊
+                for _ in 0..3 {
+                    box_blur(&mut data, w as usize, h as usize, stride, size, true);
+                    box_blur(&mut data, w as usize, h as usize, stride, size, false);
+                }
+            }
+        }
+    }
+
+    cr.save().unwrap();
+    cr.identity_matrix();
+    if cr.set_source_surface(&region, x as f64, y as f64).is_ok() {
+        cr.rectangle(x as f64, y as f64, w as f64, h as f64);
+        cr.fill().unwrap();
+    }
+    cr.restore().unwrap();
+}
+
+/// 每个 block × block 的块填充为块内的平均色
+fn pixelate(data: &mut [u8], w: usize, h: usize, stride: usize, block: usize) {
+    for by in (0..h).step_by(block) {
+        for bx in (0..w).step_by(block) {
+            let (ex, ey) = ((bx + block).min(w), (by + block).min(h));
+            let mut sum = [0u64; 4];
+            for y in by..ey {
+                for x in bx..ex {
+                    let offset = y * stride + x * 4;
+                    for (c, s) in sum.iter_mut().enumerate() {
+                        *s += data[offset + c] as u64;
+                    }
+                }
+            }
+            let n = ((ex - bx) * (ey - by)) as u64;
+            let avg = sum.map(|s| (s / n) as u8);
+            for y in by..ey {
+                for x in bx..ex {
+                    let offset = y * stride + x * 4;
+                    data[offset..offset + 4].copy_from_slice(&avg);
+                }
+            }
+        }
+    }
+}
+
+/// 水平或竖直方向的盒式模糊，边缘像素向外延伸
+fn box_blur(data: &mut [u8], w: usize, h: usize, stride: usize, radius: usize, horizontal: bool) {
+    let (len, lines) = if horizontal { (w, h) } else { (h, w) };
+    let offset = |line: usize, i: usize| {
+        if horizontal {
+            line * stride + i * 4
+        } else {
+            i * stride + line * 4
+        }
+    };
+    let window = (radius * 2 + 1) as u32;
+    let mut src = vec![[0u8; 4]; len];
+
+    for line in 0..lines {
+        for (i, pixel) in src.iter_mut().enumerate() {
+            let o = offset(line, i);
+            pixel.copy_from_slice(&data[o..o + 4]);
+        }
+        let at = |i: isize| src[i.clamp(0, len as isize - 1) as usize];
+
+        let mut sum = [0u32; 4];
+        for i in -(radius as isize)..=(radius as isize) {
+            let p = at(i);
+            for c in 0..4 {
+                sum[c] += p[c] as u32;
+            }
+        }
+        for i in 0..len {
+            let o = offset(line, i);
+            for c in 0..4 {
+                data[o + c] = (sum[c] / window) as u8;
+            }
+            let (add, remove) = (
+                at((i + radius + 1) as isize),
+                at(i as isize - radius as isize),
+            );
+            for c in 0..4 {
+                sum[c] = sum[c] + add[c] as u32 - remove[c] as u32;
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::color_picker::PickResult;
+use crate::foam_outputs::FoamOutput;
+use crate::select_rect::SubRect;
+use crate::wayland_ctx::WaylandCtx;
+
+/// 截图结束时的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Saved,
+    Copied,
+    Pinned,
+    Picked,
+    Cancelled,
+    Error,
+}
+
+impl Status {
+    /// 进程的退出码，出错为 1，取消为 2，其他为 0
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Status::Error => 1,
+            Status::Cancelled => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// 全局逻辑坐标下的矩形
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// 一个输出上的子矩形，坐标相对于输出左上角
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputRect {
+    pub output: String,
+    pub scale: f64,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// 取色模式选中的颜色
+#[derive(Debug, Clone, Serialize)]
+pub struct ColorResult {
+    pub hex: String,
+    pub rgb: String,
+    pub hsl: String,
+    /// 参与平均的像素数，单击时为 1
+    pub samples: usize,
+}
+
+impl From<PickResult> for ColorResult {
+    fn from(result: PickResult) -> Self {
+        Self {
+            hex: result.color.to_hex(),
+            rgb: result.color.to_rgb(),
+            hsl: result.color.to_hsl(),
+            samples: result.samples,
+        }
+    }
+}
+
+/// 写入的一个文件
+#[derive(Debug, Clone, Serialize)]
+pub struct FileResult {
+    pub path: String,
+    pub format: &'static str,
+    pub bytes: u64,
+    pub rect: Rect,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Timing {
+    pub started_at: String,
+    /// 从启动到退出的毫秒数
+    pub total_ms: u128,
+    /// 渲染和写入文件或剪贴板的毫秒数
+    pub save_ms: Option<u128>,
+}
+
+/// NOTE: `--json` 时在退出前输出到 stdout 的截图结果
+///
+/// path、format、bytes 取自第一个文件，拆分选区时所有文件在 files 中
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureResult {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub path: Option<String>,
+    pub format: Option<&'static str>,
+    pub bytes: Option<u64>,
+    pub files: Vec<FileResult>,
+    pub global_rect: Option<Rect>,
+    pub subrects: Vec<OutputRect>,
+    /// 测量模式中完成的测量
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub measurements: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorResult>,
+    pub timing: Timing,
+}
+
+/// 一次运行的计时
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    started: Instant,
+    started_at: DateTime<Local>,
+    /// 保存或复制耗费的时间
+    pub save: Option<Duration>,
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: Local::now(),
+            save: None,
+        }
+    }
+}
+
+impl Stopwatch {
+    fn timing(&self) -> Timing {
+        Timing {
+            started_at: self.started_at.to_rfc3339(),
+            total_ms: self.started.elapsed().as_millis(),
+            save_ms: self.save.map(|d| d.as_millis()),
+        }
+    }
+}
+
+impl CaptureResult {
+    /// 从保存的文件和当前选区收集结果，copied 为复制到剪贴板的 PNG 字节数
+    pub fn collect(
+        wl_ctx: &WaylandCtx,
+        status: Status,
+        error: Option<String>,
+        copied: Option<u64>,
+        stopwatch: &Stopwatch,
+    ) -> Self {
+        let files: Vec<FileResult> = wl_ctx
+            .saved_images
+            .iter()
+            .map(|image| {
+                let (x, y, width, height) = image.region;
+                FileResult {
+                    path: image.path.display().to_string(),
+                    format: image.format.extension(),
+                    // 文件可能已被钩子移走
+                    bytes: std::fs::metadata(&image.path)
+                        .map(|m| m.len())
+                        .unwrap_or_default(),
+                    rect: Rect {
+                        x,
+                        y,
+                        width: width as i32,
+                        height: height as i32,
+                    },
+                }
+            })
+            .collect();
+
+        let outputs = wl_ctx.foam_outputs.as_deref().unwrap_or_default();
+        let subrects: Vec<SubRect> = if wl_ctx.saved_images.is_empty() {
+            if outputs.is_empty() {
+                Vec::new()
+            } else {
+                wl_ctx.region_subrects().into_iter().flatten().collect()
+            }
+        } else {
+            wl_ctx
+                .saved_images
+                .iter()
+                .flat_map(|image| image.subrects.iter().cloned())
+                .collect()
+        };
+
+        let first = files.first();
+        Self {
+            status,
+            error,
+            path: first.map(|f| f.path.clone()),
+            format: first.map(|f| f.format).or(copied.map(|_| "png")),
+            bytes: first.map(|f| f.bytes).or(copied),
+            global_rect: bounding_rect(outputs, &subrects),
+            subrects: subrects
+                .iter()
+                .filter_map(|rect| {
+                    let output = outputs.get(rect.monitor_id)?;
+                    Some(OutputRect {
+                        output: output.name.clone(),
+                        scale: output.scale,
+                        x: rect.relative_min_x,
+                        y: rect.relative_min_y,
+                        width: rect.width,
+                        height: rect.height,
+                    })
+                })
+                .collect(),
+            files,
+            measurements: wl_ctx
+                .measure
+                .as_ref()
+                .map(|m| m.measurements.iter().map(|m| m.label()).collect())
+                .unwrap_or_default(),
+            color: None,
+            timing: stopwatch.timing(),
+        }
+    }
+
+    pub fn print(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{}", json),
+            Err(e) => log::error!("serialize result error: {}", e),
+        }
+    }
+}
+
+/// 所有子矩形在全局坐标下的外接矩形
+fn bounding_rect(outputs: &[FoamOutput], subrects: &[SubRect]) -> Option<Rect> {
+    let (x, y, r, b) = subrects
+        .iter()
+        .filter_map(|rect| {
+            let output = outputs.get(rect.monitor_id)?;
+            let x = output.global_x + rect.relative_min_x;
+            let y = output.global_y + rect.relative_min_y;
+            Some((x, y, x + rect.width, y + rect.height))
+        })
+        .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))?;
+    Some(Rect {
+        x,
+        y,
+        width: r - x,
+        height: b - y,
+    })
+}
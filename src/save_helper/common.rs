@@ -1,6 +1,10 @@
+use crate::config::ImageType;
 use crate::foam_outputs::FoamOutput;
+use crate::select_rect::SubRect;
 use crate::wayland_ctx::WaylandCtx;
+use chrono::{DateTime, Local};
 use std::error::Error;
+use std::path::{Path, PathBuf};
 
 // 捕获区域信息结构体
 pub(crate) struct CaptureInfo {
@@ -8,22 +12,21 @@ pub(crate) struct CaptureInfo {
     pub min_y: i32,
     pub total_width: u32,
     pub total_height: u32,
-    pub monitor_ids: Vec<usize>,
 }
 
 /// 计算捕获区域信息
 pub(crate) fn calculate_capture_info(
     wl_ctx: &WaylandCtx,
+    subrects: &[SubRect],
 ) -> Result<Option<CaptureInfo>, Box<dyn Error>> {
     let outputs = wl_ctx.foam_outputs.as_ref().ok_or("未初始化输出设备")?;
 
-    let mut active_info = Vec::new();
     let mut bounds: Option<(i32, i32, i32, i32)> = None;
 
-    for output in outputs {
-        let Some(rect) = &output.subrect else {
-            continue;
-        };
+    for rect in subrects {
+        let output = outputs
+            .get(rect.monitor_id)
+            .ok_or_else(|| format!("显示器{}不存在", rect.monitor_id))?;
 
         let global_x = output.global_x + rect.relative_min_x;
         let global_y = output.global_y + rect.relative_min_y;
@@ -39,8 +42,6 @@ pub(crate) fn calculate_capture_info(
             ),
             None => (global_x, global_y, right, bottom),
         });
-
-        active_info.push((rect.monitor_id, global_x, global_y));
     }
 
     let Some((x, y, r, b)) = bounds else {
@@ -52,39 +53,45 @@ pub(crate) fn calculate_capture_info(
         min_y: y,
         total_width: (r - x) as u32,
         total_height: (b - y) as u32,
-        monitor_ids: active_info.iter().map(|(id, _, _)| *id).collect(),
     }))
 }
 
-/// 创建透明背景的最终画布
+/// 创建最终画布，background 为 None 时使用透明背景
 pub(crate) fn create_final_surface(
     width: u32,
     height: u32,
+    background: Option<(f64, f64, f64)>,
 ) -> Result<cairo::ImageSurface, Box<dyn Error>> {
     let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)?;
 
     let ctx = cairo::Context::new(&surface)?;
-    ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+    match background {
+        Some((r, g, b)) => ctx.set_source_rgb(r, g, b),
+        None => ctx.set_source_rgba(0.0, 0.0, 0.0, 0.0),
+    }
     ctx.paint()?;
 
     Ok(surface)
 }
 
-/// 处理所有显示器输出
+/// 处理所有子矩形
 pub(crate) fn process_all_outputs(
     wl_ctx: &mut WaylandCtx,
+    subrects: &[SubRect],
     capture_info: &CaptureInfo,
     final_surface: &cairo::ImageSurface,
 ) -> Result<(), Box<dyn Error>> {
     let outputs = wl_ctx.foam_outputs.as_mut().ok_or("输出设备未初始化")?;
 
-    for &id in &capture_info.monitor_ids {
+    for rect in subrects {
+        let id = rect.monitor_id;
         let output = outputs
             .get_mut(id)
             .ok_or_else(|| format!("显示器{}不存在", id))?;
 
         process_single_output(
             output,
+            rect,
             wl_ctx
                 .scm
                 .base_canvas
@@ -100,18 +107,14 @@ pub(crate) fn process_all_outputs(
     Ok(())
 }
 
-/// 处理单个显示器输出
+/// 处理单个显示器输出上的一个子矩形
 pub(crate) fn process_single_output(
     output: &mut FoamOutput,
+    rect: &SubRect,
     base_canvas: &mut [u8],
     capture_info: &CaptureInfo,
     final_surface: &cairo::ImageSurface,
 ) -> Result<(), Box<dyn Error>> {
-    let rect = output
-        .subrect
-        .as_ref()
-        .ok_or_else(|| format!("显示器{}没有设置子区域", output.id))?;
-
     if rect.relative_min_x + rect.width > output.width
         || rect.relative_min_y + rect.height > output.height
     {
@@ -176,3 +179,186 @@ pub(crate) fn create_sub_surface(
 
     Ok(surface)
 }
+
+/// 在最终画布上绘制标注，只保留子矩形内的部分
+pub(crate) fn draw_annotations(
+    wl_ctx: &WaylandCtx,
+    subrects: &[SubRect],
+    capture_info: &CaptureInfo,
+    final_surface: &cairo::ImageSurface,
+) -> Result<(), Box<dyn Error>> {
+    let ctx = cairo::Context::new(final_surface)?;
+    clip_subrects(wl_ctx, subrects, capture_info, &ctx)?;
+
+    wl_ctx
+        .annotator
+        .draw(&ctx, (capture_info.min_x as f64, capture_info.min_y as f64));
+    Ok(())
+}
+
+/// 将 ctx 的绘制范围限制在子矩形内
+pub(crate) fn clip_subrects(
+    wl_ctx: &WaylandCtx,
+    subrects: &[SubRect],
+    capture_info: &CaptureInfo,
+    ctx: &cairo::Context,
+) -> Result<(), Box<dyn Error>> {
+    let outputs = wl_ctx.foam_outputs.as_ref().ok_or("输出设备未初始化")?;
+
+    for rect in subrects {
+        let output = outputs
+            .get(rect.monitor_id)
+            .ok_or_else(|| format!("显示器{}不存在", rect.monitor_id))?;
+        ctx.rectangle(
+            (output.global_x + rect.relative_min_x - capture_info.min_x) as f64,
+            (output.global_y + rect.relative_min_y - capture_info.min_y) as f64,
+            rect.width as f64,
+            rect.height as f64,
+        );
+    }
+    ctx.clip();
+    Ok(())
+}
+
+/// 渲染单张图片，只包含给定的子矩形
+fn render_subrects(
+    wl_ctx: &mut WaylandCtx,
+    subrects: Vec<SubRect>,
+    background: Option<(f64, f64, f64)>,
+) -> Result<Option<RenderedRegion>, Box<dyn Error>> {
+    let Some(capture_info) = calculate_capture_info(wl_ctx, &subrects)? else {
+        return Ok(None);
+    };
+
+    let final_surface = create_final_surface(
+        capture_info.total_width,
+        capture_info.total_height,
+        background,
+    )?;
+
+    process_all_outputs(wl_ctx, &subrects, &capture_info, &final_surface)?;
+    draw_annotations(wl_ctx, &subrects, &capture_info, &final_surface)?;
+
+    Ok(Some(RenderedRegion {
+        subrects,
+        capture_info,
+        surface: final_surface,
+    }))
+}
+
+/// 一张图片包含的子矩形和背景色
+type RegionGroup = (Vec<SubRect>, Option<(f64, f64, f64)>);
+
+/// 按配置分组选区：拆分时每个选区一组且背景透明，否则合成为一组
+fn region_groups(wl_ctx: &WaylandCtx) -> Vec<RegionGroup> {
+    let regions = wl_ctx.region_subrects();
+    if wl_ctx.config.split_regions {
+        regions.into_iter().map(|region| (region, None)).collect()
+    } else {
+        let all: Vec<SubRect> = regions.into_iter().flatten().collect();
+        vec![(all, wl_ctx.config.region_background)]
+    }
+}
+
+/// 按配置渲染所有选区：拆分时每个选区一张图片，否则合成为一张
+pub(crate) fn render_regions(
+    wl_ctx: &mut WaylandCtx,
+) -> Result<Vec<RenderedRegion>, Box<dyn Error>> {
+    let mut regions = Vec::new();
+    for (subrects, background) in region_groups(wl_ctx) {
+        if let Some(region) = render_subrects(wl_ctx, subrects, background)? {
+            regions.push(region);
+        }
+    }
+
+    Ok(regions)
+}
+
+/// 渲染完成的一张图片及其包含的子矩形
+///
+/// 矢量导出时截图位图只包含遮挡，其他标注之后以矢量绘制
+pub(crate) struct RenderedRegion {
+    pub subrects: Vec<SubRect>,
+    pub capture_info: CaptureInfo,
+    pub surface: cairo::ImageSurface,
+}
+
+/// 按配置渲染矢量导出所需的截图位图，遮挡在这里栅格化，原始像素不会进入导出文件
+pub(crate) fn render_vector_regions(
+    wl_ctx: &mut WaylandCtx,
+) -> Result<Vec<RenderedRegion>, Box<dyn Error>> {
+    let mut regions = Vec::new();
+    for (subrects, background) in region_groups(wl_ctx) {
+        let Some(capture_info) = calculate_capture_info(wl_ctx, &subrects)? else {
+            continue;
+        };
+        let bitmap = create_final_surface(
+            capture_info.total_width,
+            capture_info.total_height,
+            background,
+        )?;
+        process_all_outputs(wl_ctx, &subrects, &capture_info, &bitmap)?;
+
+        let ctx = cairo::Context::new(&bitmap)?;
+        clip_subrects(wl_ctx, &subrects, &capture_info, &ctx)?;
+        wl_ctx.annotator.draw_layer(
+            &ctx,
+            (capture_info.min_x as f64, capture_info.min_y as f64),
+            true,
+        );
+        drop(ctx);
+
+        regions.push(RenderedRegion {
+            subrects,
+            capture_info,
+            surface: bitmap,
+        });
+    }
+
+    Ok(regions)
+}
+
+/// 本次保存写入的一个文件
+#[derive(Debug, Clone)]
+pub struct SavedImage {
+    pub path: PathBuf,
+    pub format: ImageType,
+    /// 图片对应的全局逻辑区域 (x, y, width, height)，一个像素对应一个逻辑像素
+    pub region: (i32, i32, u32, u32),
+    /// 图片包含的各输出上的子矩形
+    pub subrects: Vec<SubRect>,
+    pub time: DateTime<Local>,
+}
+
+impl SavedImage {
+    pub(crate) fn new(path: PathBuf, format: ImageType, region: &RenderedRegion) -> Self {
+        let info = &region.capture_info;
+        Self {
+            path,
+            format,
+            region: (info.min_x, info.min_y, info.total_width, info.total_height),
+            subrects: region.subrects.clone(),
+            time: Local::now(),
+        }
+    }
+}
+
+/// 多张图片时在文件名后追加序号
+pub(crate) fn region_output_paths(path: &Path, count: usize) -> Vec<PathBuf> {
+    if count <= 1 {
+        return vec![path.to_path_buf()];
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or("foamshot".to_string());
+    let ext = path
+        .extension()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or("png".to_string());
+
+    (1..=count)
+        .map(|i| path.with_file_name(format!("{}-{}.{}", stem, i, ext)))
+        .collect()
+}
@@ -1,24 +1,41 @@
+use crate::config::ImageType;
 use crate::wayland_ctx::WaylandCtx;
 use image::{ImageBuffer, Rgb};
 use log::warn;
 use std::io::Write;
+use std::path::Path;
 
-use super::common::{calculate_capture_info, create_final_surface, process_all_outputs};
+use super::common::{SavedImage, region_output_paths, render_regions};
+use super::metadata::Metadata;
 
 pub fn save_to_jpg(wl_ctx: &mut WaylandCtx, quality: u8) -> Result<(), Box<dyn std::error::Error>> {
-    let capture_info = match calculate_capture_info(wl_ctx)? {
-        Some(info) => info,
-        None => {
-            warn!("未找到有效截图区域");
-            return Ok(());
-        }
-    };
+    let regions = render_regions(wl_ctx)?;
+    if regions.is_empty() {
+        warn!("未找到有效截图区域");
+        return Ok(());
+    }
 
-    let mut final_surface =
-        create_final_surface(capture_info.total_width, capture_info.total_height)?;
+    let paths = region_output_paths(&wl_ctx.config.output_path, regions.len());
 
-    process_all_outputs(wl_ctx, &capture_info, &final_surface)?;
+    for (region, output_path) in regions.into_iter().zip(paths) {
+        let saved = SavedImage::new(output_path.clone(), ImageType::Jpg, &region);
+        let metadata = wl_ctx
+            .config
+            .metadata
+            .then(|| Metadata::new(wl_ctx, &saved));
+        write_jpg(region.surface, &output_path, quality, metadata)?;
+        wl_ctx.saved_images.push(saved);
+    }
 
+    Ok(())
+}
+
+fn write_jpg(
+    mut final_surface: cairo::ImageSurface,
+    output_path: &Path,
+    quality: u8,
+    metadata: Option<Metadata>,
+) -> Result<(), Box<dyn std::error::Error>> {
     // 将Cairo surface转换为RGB格式
     let width = final_surface.width() as u32;
     let height = final_surface.height() as u32;
@@ -41,15 +58,7 @@ pub fn save_to_jpg(wl_ctx: &mut WaylandCtx, quality: u8) -> Result<(), Box<dyn s
         }
     }
 
-    // 保存为JPEG
-    let output_path = wl_ctx.config.output_path.clone();
-    let mut output_file = std::fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(output_path.clone())
-        .map_err(|e| format!("创建文件失败: {}", e))?;
-
-    // 将ImageBuffer编码为JPEG并写入文件
+    // 将ImageBuffer编码为JPEG
     let mut jpeg_data = Vec::new();
     let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_data, quality);
 
@@ -61,6 +70,16 @@ pub fn save_to_jpg(wl_ctx: &mut WaylandCtx, quality: u8) -> Result<(), Box<dyn s
             image::ExtendedColorType::Rgb8,
         )
         .map_err(|e| format!("JPEG编码失败: {}", e))?;
+    if let Some(metadata) = metadata {
+        jpeg_data = metadata.embed_jpeg(jpeg_data)?;
+    }
+
+    // 编码完成后再创建文件，避免失败时留下空文件
+    let mut output_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output_path)
+        .map_err(|e| format!("创建文件失败: {}", e))?;
 
     output_file
         .write_all(&jpeg_data)
@@ -0,0 +1,386 @@
+use crate::save_helper::SavedImage;
+use crate::wayland_ctx::WaylandCtx;
+use chrono::{DateTime, Local};
+use log::warn;
+use std::error::Error;
+
+/// PNG 文件签名和 IHDR 块的长度，元数据块插在 IHDR 之后
+const PNG_SIGNATURE_LEN: usize = 8;
+const PNG_IHDR_LEN: usize = 4 + 4 + 13 + 4;
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// XMP 中 foamshot 字段的命名空间
+const XMP_NAMESPACE: &str = "urn:foamshot:xmp:1.0/";
+/// JPEG 段的长度字段只有 16 位，包含长度字段本身的 2 字节
+const JPEG_SEGMENT_MAX: usize = u16::MAX as usize - 2;
+/// XMP 中窗口标题的最大字符数，转义后仍远小于段长度上限
+const XMP_TITLE_MAX_CHARS: usize = 1024;
+
+/// NOTE: 写入图片文件的截图信息，PNG 使用 tEXt/iTXt 块，JPEG 使用 EXIF 和 XMP
+///
+/// 窗口信息只在单击截取窗口时存在
+pub(crate) struct Metadata {
+    created: DateTime<Local>,
+    software: String,
+    /// 全局逻辑区域 (x, y, width, height)
+    region: (i32, i32, u32, u32),
+    /// 图片包含的输出名称和缩放比例
+    outputs: Vec<(String, f64)>,
+    app_id: Option<String>,
+    title: Option<String>,
+}
+
+impl Metadata {
+    pub(crate) fn new(wl_ctx: &WaylandCtx, image: &SavedImage) -> Self {
+        let foam_outputs = wl_ctx.foam_outputs.as_deref().unwrap_or_default();
+        let mut ids: Vec<usize> = image.subrects.iter().map(|r| r.monitor_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let window = wl_ctx.window_info.as_ref();
+
+        Self {
+            created: image.time,
+            software: format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+            region: image.region,
+            outputs: ids
+                .iter()
+                .filter_map(|&id| foam_outputs.get(id))
+                .map(|o| (o.name.clone(), o.scale))
+                .collect(),
+            app_id: window.map(|w| w.app_id.clone()).filter(|s| !s.is_empty()),
+            title: window.map(|w| w.title.clone()).filter(|s| !s.is_empty()),
+        }
+    }
+
+    fn region_text(&self) -> String {
+        let (x, y, width, height) = self.region;
+        format!("{},{} {}x{}", x, y, width, height)
+    }
+
+    fn outputs_text(&self) -> String {
+        let names: Vec<&str> = self.outputs.iter().map(|(name, _)| name.as_str()).collect();
+        names.join(",")
+    }
+
+    fn scales_text(&self) -> String {
+        let scales: Vec<String> = self.outputs.iter().map(|(_, s)| s.to_string()).collect();
+        scales.join(",")
+    }
+
+    /// 关键字和值，关键字使用 PNG 规范中的名称或加上 Capture/Window 前缀
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("Creation Time", self.created.to_rfc3339()),
+            ("Software", self.software.clone()),
+            ("Capture Region", self.region_text()),
+            ("Capture Outputs", self.outputs_text()),
+            ("Capture Scales", self.scales_text()),
+        ];
+        if let Some(app_id) = self.app_id.as_ref() {
+            entries.push(("Window App ID", app_id.clone()));
+        }
+        if let Some(title) = self.title.as_ref() {
+            entries.push(("Window Title", title.clone()));
+        }
+        entries
+    }
+
+    /// 在 IHDR 之后插入文本块，纯 ASCII 的值使用 tEXt，其他使用未压缩的 iTXt
+    pub(crate) fn embed_png(&self, png: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        let split = PNG_SIGNATURE_LEN + PNG_IHDR_LEN;
+        if png.len() < split || &png[12..16] != b"IHDR" {
+            return Err("无效的PNG数据".into());
+        }
+
+        let mut out = Vec::with_capacity(png.len() + 512);
+        out.extend_from_slice(&png[..split]);
+        for (keyword, value) in self.entries() {
+            let mut data = keyword.as_bytes().to_vec();
+            data.push(0);
+            if value.is_ascii() {
+                data.extend_from_slice(value.as_bytes());
+                write_png_chunk(&mut out, b"tEXt", &data);
+            } else {
+                // 不压缩，语言标签和翻译后的关键字为空
+                data.extend_from_slice(&[0, 0, 0, 0]);
+                data.extend_from_slice(value.as_bytes());
+                write_png_chunk(&mut out, b"iTXt", &data);
+            }
+        }
+        out.extend_from_slice(&png[split..]);
+        Ok(out)
+    }
+
+    /// 在 SOI 和 JFIF APP0 段之后插入 EXIF 和 XMP 两个 APP1 段，超出段长度的段被跳过
+    pub(crate) fn embed_jpeg(&self, jpeg: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        if jpeg.len() < 4 || jpeg[..2] != [0xFF, 0xD8] {
+            return Err("无效的JPEG数据".into());
+        }
+        let mut split = 2;
+        if jpeg.len() >= 6 && jpeg[2..4] == [0xFF, 0xE0] {
+            let len = u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+            split += 2 + len;
+        }
+
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend_from_slice(&self.tiff());
+        let mut xmp = XMP_HEADER.to_vec();
+        xmp.extend_from_slice(self.xmp().as_bytes());
+
+        let mut out = Vec::with_capacity(jpeg.len() + exif.len() + xmp.len() + 8);
+        out.extend_from_slice(&jpeg[..split]);
+        for segment in [exif, xmp] {
+            if segment.len() > JPEG_SEGMENT_MAX {
+                warn!("JPEG元数据过长，已跳过");
+                continue;
+            }
+            write_jpeg_segment(&mut out, 0xE1, &segment);
+        }
+        out.extend_from_slice(&jpeg[split..]);
+        Ok(out)
+    }
+
+    /// 只包含 IFD0 的小端 TIFF 结构：ImageDescription、Software、DateTime
+    ///
+    /// EXIF 的 ASCII 字段不能保存非 ASCII 文字，窗口标题等完整信息放在 XMP 中
+    fn tiff(&self) -> Vec<u8> {
+        let (x, y, width, height) = self.region;
+        let mut description = format!(
+            "{}x{} at {},{} on {}",
+            width,
+            height,
+            x,
+            y,
+            self.outputs_text()
+        );
+        if let Some(app_id) = self.app_id.as_ref() {
+            description.push_str(&format!(" ({})", app_id));
+        }
+        let fields: [(u16, String); 3] = [
+            (0x010E, ascii_only(&description)),
+            (0x0131, ascii_only(&self.software)),
+            (0x0132, self.created.format("%Y:%m:%d %H:%M:%S").to_string()),
+        ];
+
+        // 头部 8 字节 + 条目数 2 字节 + 每个条目 12 字节 + 下一个 IFD 偏移 4 字节
+        let mut data_offset = 8 + 2 + fields.len() * 12 + 4;
+        let mut entries = Vec::new();
+        let mut values = Vec::new();
+        for (tag, value) in &fields {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            entries.extend_from_slice(&tag.to_le_bytes());
+            // 类型 2 为 ASCII
+            entries.extend_from_slice(&2u16.to_le_bytes());
+            entries.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            if bytes.len() <= 4 {
+                bytes.resize(4, 0);
+                entries.extend_from_slice(&bytes);
+            } else {
+                entries.extend_from_slice(&(data_offset as u32).to_le_bytes());
+                data_offset += bytes.len();
+                values.extend_from_slice(&bytes);
+            }
+        }
+
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        tiff.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+        tiff.extend_from_slice(&entries);
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(&values);
+        tiff
+    }
+
+    fn xmp(&self) -> String {
+        let mut fields = format!(
+            "<xmp:CreateDate>{}</xmp:CreateDate>\
+             <xmp:CreatorTool>{}</xmp:CreatorTool>\
+             <foamshot:Region>{}</foamshot:Region>\
+             <foamshot:Outputs>{}</foamshot:Outputs>\
+             <foamshot:Scales>{}</foamshot:Scales>",
+            self.created.to_rfc3339(),
+            xml_escape(&self.software),
+            self.region_text(),
+            xml_escape(&self.outputs_text()),
+            self.scales_text(),
+        );
+        if let Some(app_id) = self.app_id.as_ref() {
+            fields.push_str(&format!(
+                "<foamshot:AppId>{}</foamshot:AppId>",
+                xml_escape(app_id)
+            ));
+        }
+        if let Some(title) = self.title.as_ref() {
+            let title: String = title.chars().take(XMP_TITLE_MAX_CHARS).collect();
+            fields.push_str(&format!(
+                "<foamshot:WindowTitle>{}</foamshot:WindowTitle>",
+                xml_escape(&title)
+            ));
+        }
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+             <rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\" xmlns:foamshot=\"{}\">\
+             {}</rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end=\"w\"?>",
+            XMP_NAMESPACE, fields
+        )
+    }
+}
+
+fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let mut crc_data = kind.to_vec();
+    crc_data.extend_from_slice(data);
+    out.extend_from_slice(&crc32(&crc_data).to_be_bytes());
+}
+
+/// data 的长度不能超过 `JPEG_SEGMENT_MAX`
+fn write_jpeg_segment(out: &mut Vec<u8>, marker: u8, data: &[u8]) {
+    // 段长度包含长度字段本身的 2 字节
+    let len = (data.len() + 2) as u16;
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// PNG 块使用的 CRC-32 (IEEE)
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn ascii_only(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii() && !c.is_ascii_control())
+        .collect()
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use image::{ImageEncoder, RgbImage};
+
+    fn metadata(title: &str) -> Metadata {
+        Metadata {
+            created: Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap(),
+            software: "foamshot 0.0.0".to_string(),
+            region: (10, -20, 30, 40),
+            outputs: vec![("DP-1".to_string(), 1.0), ("HDMI-A-1".to_string(), 1.5)],
+            app_id: Some("org.example.App".to_string()),
+            title: Some(title.to_string()),
+        }
+    }
+
+    fn encode(format: image::ImageFormat) -> Vec<u8> {
+        let image = RgbImage::from_fn(4, 3, |x, y| image::Rgb([x as u8 * 60, y as u8 * 80, 128]));
+        let mut data = Vec::new();
+        match format {
+            image::ImageFormat::Png => image::codecs::png::PngEncoder::new(&mut data)
+                .write_image(image.as_raw(), 4, 3, image::ExtendedColorType::Rgb8)
+                .unwrap(),
+            _ => image::codecs::jpeg::JpegEncoder::new(&mut data)
+                .write_image(image.as_raw(), 4, 3, image::ExtendedColorType::Rgb8)
+                .unwrap(),
+        }
+        data
+    }
+
+    /// 按顺序返回 PNG 中的块类型和数据，同时检查 CRC
+    fn png_chunks(png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        let mut chunks = Vec::new();
+        let mut pos = PNG_SIGNATURE_LEN;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = png[pos + 4..pos + 8].try_into().unwrap();
+            let data = &png[pos + 8..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(&png[pos + 4..pos + 8 + len]));
+            chunks.push((kind, data.to_vec()));
+            pos += 12 + len;
+        }
+        chunks
+    }
+
+    #[test]
+    fn crc32_matches_iend() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn embedded_png_decodes_and_keeps_text() {
+        let png = metadata("终端 — ~/foam")
+            .embed_png(encode(image::ImageFormat::Png))
+            .unwrap();
+        let decoded = image::load_from_memory(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (4, 3));
+
+        let chunks = png_chunks(&png);
+        assert_eq!(&chunks[0].0, b"IHDR");
+        let text = |kind: &[u8; 4], keyword: &str| {
+            chunks
+                .iter()
+                .filter(|(k, _)| k == kind)
+                .find_map(|(_, data)| data.strip_prefix(format!("{}\0", keyword).as_bytes()))
+                .map(|value| value.to_vec())
+        };
+        assert_eq!(
+            text(b"tEXt", "Capture Region").unwrap(),
+            b"10,-20 30x40".to_vec()
+        );
+        assert_eq!(
+            text(b"tEXt", "Capture Outputs").unwrap(),
+            b"DP-1,HDMI-A-1".to_vec()
+        );
+        // 压缩标志、压缩方法、语言标签和翻译后的关键字之后是 UTF-8 文本
+        let title = text(b"iTXt", "Window Title").unwrap();
+        assert_eq!(&title[..4], &[0, 0, 0, 0]);
+        assert_eq!(std::str::from_utf8(&title[4..]).unwrap(), "终端 — ~/foam");
+    }
+
+    #[test]
+    fn embedded_jpeg_decodes() {
+        let jpeg = metadata("<title & \"quotes\">")
+            .embed_jpeg(encode(image::ImageFormat::Jpeg))
+            .unwrap();
+        let decoded = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (4, 3));
+
+        let xmp = String::from_utf8_lossy(&jpeg);
+        assert!(xmp.contains(
+            "<foamshot:WindowTitle>&lt;title &amp; &quot;quotes&quot;&gt;</foamshot:WindowTitle>"
+        ));
+        assert!(jpeg.windows(6).any(|w| w == b"Exif\0\0"));
+    }
+
+    #[test]
+    fn long_title_does_not_fail_jpeg() {
+        let jpeg = metadata(&"\"".repeat(100_000))
+            .embed_jpeg(encode(image::ImageFormat::Jpeg))
+            .unwrap();
+        assert!(image::load_from_memory(&jpeg).is_ok());
+        assert!(jpeg.windows(XMP_HEADER.len()).any(|w| w == XMP_HEADER));
+    }
+}
@@ -1,8 +1,17 @@
 mod common;
 mod jpg;
+mod metadata;
 mod png;
+mod vector;
 mod wl_clipboard;
 
+pub use common::SavedImage;
+pub(crate) use common::{RenderedRegion, render_regions};
 pub use jpg::save_to_jpg;
+pub(crate) use png::encode_png;
 pub use png::save_to_png;
-pub use wl_clipboard::save_to_wl_clipboard;
+pub use vector::save_to_vector;
+pub use wl_clipboard::{
+    copy_data_to_wl_clipboard, copy_png_to_wl_clipboard, copy_text_to_wl_clipboard,
+    save_to_wl_clipboard,
+};
@@ -1,35 +1,53 @@
+use crate::config::ImageType;
 use crate::wayland_ctx::WaylandCtx;
 use log::warn;
 use std::io::Write;
 
-use super::common::{calculate_capture_info, create_final_surface, process_all_outputs};
+use super::common::{SavedImage, region_output_paths, render_regions};
+use super::metadata::Metadata;
 
 pub fn save_to_png(wl_ctx: &mut WaylandCtx) -> Result<(), Box<dyn std::error::Error>> {
-    let capture_info = match calculate_capture_info(wl_ctx)? {
-        Some(info) => info,
-        None => {
-            warn!("未找到有效截图区域");
-            return Ok(());
-        }
-    };
+    let regions = render_regions(wl_ctx)?;
+    if regions.is_empty() {
+        warn!("未找到有效截图区域");
+        return Ok(());
+    }
 
-    let final_surface = create_final_surface(capture_info.total_width, capture_info.total_height)?;
+    let paths = region_output_paths(&wl_ctx.config.output_path, regions.len());
 
-    process_all_outputs(wl_ctx, &capture_info, &final_surface)?;
+    for (region, output_path) in regions.iter().zip(paths) {
+        let saved = SavedImage::new(output_path.clone(), ImageType::Png, region);
+        let png_data = encode_png(wl_ctx, &saved, &region.surface)?;
 
-    let output_path = wl_ctx.config.output_path.clone();
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(output_path)
+            .map_err(|e| format!("创建文件失败: {}", e))?;
 
-    let mut file = std::fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(output_path.clone())
-        .map_err(|e| format!("创建文件失败: {}", e))?;
+        file.write_all(&png_data)
+            .map_err(|e| format!("写入PNG失败: {}", e))?;
 
-    final_surface
-        .write_to_png(&mut file)
-        .map_err(|e| format!("写入PNG失败: {}", e))?;
-
-    file.flush().map_err(|e| format!("刷新文件失败: {}", e))?;
+        file.flush().map_err(|e| format!("刷新文件失败: {}", e))?;
+        wl_ctx.saved_images.push(saved);
+    }
 
     Ok(())
 }
+
+/// 编码为 PNG，未关闭元数据时写入截图信息
+pub(crate) fn encode_png(
+    wl_ctx: &WaylandCtx,
+    image: &SavedImage,
+    surface: &cairo::ImageSurface,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut png_data = Vec::new();
+    surface
+        .write_to_png(&mut png_data)
+        .map_err(|e| format!("编码PNG失败: {}", e))?;
+
+    if !wl_ctx.config.metadata {
+        return Ok(png_data);
+    }
+    Metadata::new(wl_ctx, image).embed_png(png_data)
+}
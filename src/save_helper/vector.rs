@@ -0,0 +1,80 @@
+use crate::config::ImageType;
+use crate::wayland_ctx::WaylandCtx;
+use log::warn;
+use std::ops::Deref;
+
+use super::common::{
+    RenderedRegion, SavedImage, clip_subrects, region_output_paths, render_vector_regions,
+};
+
+/// 保存为 SVG 或 PDF：截图作为位图嵌入，标注以矢量路径和文字绘制在上层
+pub fn save_to_vector(
+    wl_ctx: &mut WaylandCtx,
+    image_type: ImageType,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let regions = render_vector_regions(wl_ctx)?;
+    if regions.is_empty() {
+        warn!("未找到有效截图区域");
+        return Ok(());
+    }
+
+    let paths = region_output_paths(&wl_ctx.config.output_path, regions.len());
+
+    for (region, output_path) in regions.iter().zip(paths) {
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(output_path.clone())
+            .map_err(|e| format!("创建文件失败: {}", e))?;
+
+        let (width, height) = (
+            region.capture_info.total_width as f64,
+            region.capture_info.total_height as f64,
+        );
+        match image_type {
+            ImageType::Svg => {
+                let surface = cairo::SvgSurface::for_stream(width, height, file)?;
+                write_vector(wl_ctx, region, &surface)?;
+            }
+            ImageType::Pdf => {
+                let surface = cairo::PdfSurface::for_stream(width, height, file)?;
+                write_vector(wl_ctx, region, &surface)?;
+            }
+            _ => return Err(format!("{:?} 不是矢量格式", image_type).into()),
+        }
+        wl_ctx
+            .saved_images
+            .push(SavedImage::new(output_path, image_type, region));
+    }
+
+    Ok(())
+}
+
+/// 在矢量 surface 上绘制位图和标注，并写入文件
+fn write_vector(
+    wl_ctx: &WaylandCtx,
+    region: &RenderedRegion,
+    surface: &impl Deref<Target = cairo::Surface>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    {
+        let ctx = cairo::Context::new(surface.deref())?;
+        ctx.set_source_surface(&region.surface, 0.0, 0.0)?;
+        ctx.paint()?;
+
+        clip_subrects(wl_ctx, &region.subrects, &region.capture_info, &ctx)?;
+        wl_ctx.annotator.draw_layer(
+            &ctx,
+            (
+                region.capture_info.min_x as f64,
+                region.capture_info.min_y as f64,
+            ),
+            false,
+        );
+        ctx.show_page()?;
+    }
+
+    surface
+        .finish_output_stream()
+        .map_err(|e| format!("写入矢量文件失败: {}", e.error))?;
+    Ok(())
+}
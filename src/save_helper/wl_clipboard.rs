@@ -2,25 +2,19 @@ use crate::wayland_ctx::WaylandCtx;
 use std::io::Write;
 use std::process::{Command, Stdio};
 
-// TODO: the batter way
-pub fn save_to_wl_clipboard(wl_ctx: &mut WaylandCtx) -> Result<(), Box<dyn std::error::Error>> {
-    if !wl_ctx.config.auto_copy {
-        return Ok(());
-    }
-
-    let file_content =
-        std::fs::read(&wl_ctx.config.output_path).map_err(|e| format!("读取文件失败: {}", e))?;
+use super::common::render_regions;
 
+/// 启动 wl-copy 并将数据写入其标准输入
+fn pipe_to_wl_copy(args: &[&str], data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
     let mut child = Command::new("wl-copy")
-        .arg("--type")
-        .arg("image/png")
+        .args(args)
         .stdin(Stdio::piped())
         .spawn()
         .map_err(|e| format!("启动wl-copy失败: {}", e))?;
 
     if let Some(stdin) = child.stdin.as_mut() {
         stdin
-            .write_all(&file_content)
+            .write_all(data)
             .map_err(|e| format!("写入数据到wl-copy失败: {}", e))?;
     }
 
@@ -28,24 +22,58 @@ pub fn save_to_wl_clipboard(wl_ctx: &mut WaylandCtx) -> Result<(), Box<dyn std::
         .wait()
         .map_err(|e| format!("等待 wl-copy 进程结束失败: {}", e))?;
 
-    // 然后复制文件路径为文本类型
-    let file_path = wl_ctx.config.output_path.to_string_lossy();
-    let mut child = Command::new("wl-copy")
-        .arg("--primary") // 使用主选区存储路径
-        .stdin(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("启动wl-copy (path)失败: {}", e))?;
+    Ok(())
+}
 
-    // 写入路径到 wl-copy 的标准输入
-    if let Some(stdin) = child.stdin.as_mut() {
-        stdin
-            .write_all(file_path.as_bytes())
-            .map_err(|e| format!("写入路径到wl-copy失败: {}", e))?;
+// TODO: the batter way
+pub fn save_to_wl_clipboard(wl_ctx: &mut WaylandCtx) -> Result<(), Box<dyn std::error::Error>> {
+    if !wl_ctx.config.auto_copy {
+        return Ok(());
     }
 
-    child
-        .wait()
-        .map_err(|e| format!("等待 wl-copy (path)进程结束失败: {}", e))?;
+    // 多个文件时复制第一个
+    let Some(output_path) = wl_ctx.saved_images.first().map(|image| &image.path) else {
+        return Ok(());
+    };
+
+    let file_content = std::fs::read(output_path).map_err(|e| format!("读取文件失败: {}", e))?;
+
+    pipe_to_wl_copy(&["--type", "image/png"], &file_content)?;
+
+    // 然后复制文件路径为文本类型，使用主选区存储路径
+    let file_path = output_path.to_string_lossy();
+    pipe_to_wl_copy(&["--primary"], file_path.as_bytes())?;
 
     Ok(())
 }
+
+/// 不写入文件，直接将截图编码为 PNG 复制到剪贴板，返回 PNG 的字节数
+pub fn copy_png_to_wl_clipboard(
+    wl_ctx: &mut WaylandCtx,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let regions = render_regions(wl_ctx)?;
+    // 多个选区时复制第一个
+    let region = regions.first().ok_or("未找到有效截图区域")?;
+
+    let mut png_data = Vec::new();
+    region
+        .surface
+        .write_to_png(&mut png_data)
+        .map_err(|e| format!("编码PNG失败: {}", e))?;
+
+    pipe_to_wl_copy(&["--type", "image/png"], &png_data)?;
+    Ok(png_data.len())
+}
+
+/// 复制纯文本到剪贴板，用于取色等非图片结果
+pub fn copy_text_to_wl_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    copy_data_to_wl_clipboard("text/plain", text.as_bytes())
+}
+
+/// 以指定的 MIME 类型复制数据到剪贴板
+pub fn copy_data_to_wl_clipboard(
+    mime_type: &str,
+    data: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    pipe_to_wl_copy(&["--type", mime_type], data)
+}
@@ -22,6 +22,37 @@ impl SelectRect {
             rect_origin: None,
         }
     }
+    /// 比较两个选区的位置和尺寸，忽略拖动缓存
+    pub fn same_area(&self, other: &SelectRect) -> bool {
+        (self.sx, self.sy, self.ex, self.ey) == (other.sx, other.sy, other.ex, other.ey)
+    }
+
+    /// 以 base 的中心为基准按 factor 缩放
+    pub fn scale_around_center(&mut self, base: &SelectRect, factor: f64) {
+        let cx = (base.sx + base.ex) as f64 / 2.0;
+        let cy = (base.sy + base.ey) as f64 / 2.0;
+        let half_w = ((base.ex - base.sx) as f64 * factor / 2.0).max(1.0);
+        let half_h = ((base.ey - base.sy) as f64 * factor / 2.0).max(1.0);
+        self.sx = (cx - half_w).round() as i32;
+        self.sy = (cy - half_h).round() as i32;
+        self.ex = (cx + half_w).round() as i32;
+        self.ey = (cy + half_h).round() as i32;
+    }
+
+    /// 四边同时向外扩展 delta，保持中心不变，delta 为负时缩小且至少保留 1 像素
+    pub fn grow(&mut self, delta: i32) {
+        let limit = ((self.ex - self.sx).min(self.ey - self.sy) - 1) / 2;
+        let delta = delta.max(-limit.max(0));
+        self.sx -= delta;
+        self.sy -= delta;
+        self.ex += delta;
+        self.ey += delta;
+    }
+
+    pub fn contains(&self, gx: i32, gy: i32) -> bool {
+        gx >= self.sx && gx <= self.ex && gy >= self.sy && gy <= self.ey
+    }
+
     /// NOTE: 需要返回新的Action
     pub fn edit(&mut self, start_pos: (f64, f64), end_pos: (f64, f64), act: Action) -> Action {
         // 检查是否需要重置移动状态：如果不是移动操作，或者是新的移动开始（start_pos 变化）
@@ -241,3 +272,157 @@ impl SubRect {
         }
     }
 }
+
+/// NOTE: 磁吸目标，保存所有可吸附的全局竖直边 x 与水平边 y
+#[derive(Debug, Clone, Default)]
+pub struct SnapTargets {
+    pub xs: Vec<i32>,
+    pub ys: Vec<i32>,
+}
+
+impl SnapTargets {
+    pub fn push_rect(&mut self, sx: i32, sy: i32, ex: i32, ey: i32) {
+        self.xs.extend([sx, ex]);
+        self.ys.extend([sy, ey]);
+    }
+
+    /// 在阈值内寻找离 v 最近的目标，返回需要的偏移量
+    fn nearest(targets: &[i32], v: i32, threshold: i32) -> Option<i32> {
+        targets
+            .iter()
+            .map(|t| t - v)
+            .filter(|d| d.abs() <= threshold)
+            .min_by_key(|d| d.abs())
+    }
+
+    pub fn snap_x(&self, x: i32, threshold: i32) -> i32 {
+        x + Self::nearest(&self.xs, x, threshold).unwrap_or(0)
+    }
+
+    pub fn snap_y(&self, y: i32, threshold: i32) -> i32 {
+        y + Self::nearest(&self.ys, y, threshold).unwrap_or(0)
+    }
+}
+
+impl SelectRect {
+    /// 对正在编辑的边进行磁吸
+    /// * edge: 正在移动的边/角，Move 时整体平移
+    /// * threshold: 吸附距离（单位：像素）
+    pub fn snap(&mut self, edge: EditAction, targets: &SnapTargets, threshold: i32) {
+        if threshold <= 0 {
+            return;
+        }
+        let t = threshold;
+
+        match edge {
+            EditAction::Move => {
+                // 平移时选择左右/上下两条边中偏移最小的一条，保持尺寸不变
+                let dx = [self.sx, self.ex]
+                    .iter()
+                    .filter_map(|v| SnapTargets::nearest(&targets.xs, *v, t))
+                    .min_by_key(|d| d.abs())
+                    .unwrap_or(0);
+                let dy = [self.sy, self.ey]
+                    .iter()
+                    .filter_map(|v| SnapTargets::nearest(&targets.ys, *v, t))
+                    .min_by_key(|d| d.abs())
+                    .unwrap_or(0);
+                self.sx += dx;
+                self.ex += dx;
+                self.sy += dy;
+                self.ey += dy;
+            }
+            EditAction::None => {}
+            _ => {
+                let (left, right, top, bottom) = match edge {
+                    EditAction::Left => (true, false, false, false),
+                    EditAction::Right => (false, true, false, false),
+                    EditAction::Top => (false, false, true, false),
+                    EditAction::Bottom => (false, false, false, true),
+                    EditAction::TopLeft => (true, false, true, false),
+                    EditAction::TopRight => (false, true, true, false),
+                    EditAction::BottomLeft => (true, false, false, true),
+                    _ => (false, true, false, true),
+                };
+                if left {
+                    self.sx = targets.snap_x(self.sx, t);
+                }
+                if right {
+                    self.ex = targets.snap_x(self.ex, t);
+                }
+                if top {
+                    self.sy = targets.snap_y(self.sy, t);
+                }
+                if bottom {
+                    self.ey = targets.snap_y(self.ey, t);
+                }
+
+                // 拖动角时宽高接近则吸附为等宽高
+                let (w, h) = (self.ex - self.sx, self.ey - self.sy);
+                if (left || right) && (top || bottom) && w != h && (w - h).abs() <= t {
+                    if top {
+                        self.sy = self.ey - w;
+                    } else {
+                        self.ey = self.sy + w;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(rect: &SelectRect) -> (i32, i32, i32, i32) {
+        (rect.sx, rect.sy, rect.ex, rect.ey)
+    }
+
+    #[test]
+    fn move_picks_smaller_offset() {
+        let targets = SnapTargets {
+            xs: vec![0, 115],
+            ys: vec![],
+        };
+        let mut rect = SelectRect::new(10, 10, 110, 60);
+        rect.snap(EditAction::Move, &targets, 12);
+        // 左边需要 -10，右边只需要 +5，尺寸不变
+        assert_eq!(area(&rect), (15, 10, 115, 60));
+    }
+
+    #[test]
+    fn only_dragged_corner_snaps() {
+        let mut targets = SnapTargets::default();
+        targets.push_rect(0, 0, 100, 50);
+        let mut rect = SelectRect::new(3, 3, 97, 47);
+        rect.snap(EditAction::BottomRight, &targets, 8);
+        assert_eq!(area(&rect), (3, 3, 100, 50));
+
+        let mut rect = SelectRect::new(3, 3, 97, 47);
+        rect.snap(EditAction::TopLeft, &targets, 8);
+        assert_eq!(area(&rect), (0, 0, 97, 47));
+    }
+
+    #[test]
+    fn square_snap_within_threshold() {
+        let targets = SnapTargets::default();
+        let mut rect = SelectRect::new(0, 0, 100, 95);
+        rect.snap(EditAction::BottomRight, &targets, 8);
+        assert_eq!(area(&rect), (0, 0, 100, 100));
+
+        let mut rect = SelectRect::new(0, 5, 100, 100);
+        rect.snap(EditAction::TopLeft, &targets, 8);
+        assert_eq!(area(&rect), (0, 0, 100, 100));
+
+        // 宽高差超过阈值时不变
+        let mut rect = SelectRect::new(0, 0, 100, 90);
+        rect.snap(EditAction::BottomRight, &targets, 8);
+        assert_eq!(area(&rect), (0, 0, 100, 90));
+
+        // 只拖动一条边时不吸附为等宽高
+        let mut rect = SelectRect::new(0, 0, 100, 95);
+        rect.snap(EditAction::Right, &targets, 8);
+        assert_eq!(area(&rect), (0, 0, 100, 95));
+    }
+}
@@ -0,0 +1,96 @@
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::foam_outputs::FoamOutput;
+use crate::select_rect::SelectRect;
+
+/// 保存选区时的输出布局，用于判断布局是否变化
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputLayout {
+    pub name: String,
+    pub global_x: i32,
+    pub global_y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub scale: f64,
+}
+
+impl OutputLayout {
+    pub fn from_outputs(outputs: &[FoamOutput]) -> Vec<Self> {
+        outputs
+            .iter()
+            .map(|o| Self {
+                name: o.name.clone(),
+                global_x: o.global_x,
+                global_y: o.global_y,
+                width: o.width,
+                height: o.height,
+                scale: o.scale,
+            })
+            .collect()
+    }
+}
+
+/// NOTE: 跨会话保存的状态，位于 `$XDG_STATE_HOME/foamshot/last.json`
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LastState {
+    /// 上一次保存时的全局选区 (sx, sy, ex, ey)
+    pub global_rect: Option<(i32, i32, i32, i32)>,
+    /// 上一次保存时的输出布局
+    #[serde(default)]
+    pub outputs: Vec<OutputLayout>,
+    /// 上一次使用步骤序号时的下一个编号
+    #[serde(default)]
+    pub next_step: Option<u32>,
+}
+
+impl LastState {
+    fn state_path() -> Option<PathBuf> {
+        let dirs = ProjectDirs::from("", "", env!("CARGO_PKG_NAME"))?;
+        let dir = dirs.state_dir().unwrap_or(dirs.data_local_dir());
+        Some(dir.join("last.json"))
+    }
+
+    /// 读取失败时返回空状态，不影响截图流程
+    pub fn load() -> Self {
+        Self::state_path()
+            .and_then(|p| fs::read(p).ok())
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::state_path().ok_or("无法确定状态目录")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn last_rect(&self) -> Option<SelectRect> {
+        self.global_rect
+            .map(|(sx, sy, ex, ey)| SelectRect::new(sx, sy, ex, ey))
+    }
+
+    pub fn set_rect(&mut self, rect: &SelectRect, outputs: &[FoamOutput]) {
+        self.global_rect = Some((rect.sx, rect.sy, rect.ex, rect.ey));
+        self.outputs = OutputLayout::from_outputs(outputs);
+    }
+
+    /// 与当前输出布局比较，未记录布局时视为一致
+    pub fn layout_matches(&self, outputs: &[FoamOutput]) -> bool {
+        if self.outputs.is_empty() {
+            return true;
+        }
+        let mut saved = self.outputs.clone();
+        let mut current = OutputLayout::from_outputs(outputs);
+        saved.sort_by(|a, b| a.name.cmp(&b.name));
+        current.sort_by(|a, b| a.name.cmp(&b.name));
+        saved == current
+    }
+}
@@ -0,0 +1,85 @@
+use wayland_client::{QueueHandle, protocol::wl_seat};
+use wayland_protocols::wp::text_input::zv3::client::{
+    zwp_text_input_manager_v3, zwp_text_input_v3,
+};
+
+use crate::foamshot::FoamShot;
+
+/// NOTE: 基于 zwp_text_input_v3 的输入法支持，合成器或输入法不支持时退回到 xkb 按键文字
+#[derive(Default)]
+pub struct TextInputHelper {
+    pub manager: Option<(zwp_text_input_manager_v3::ZwpTextInputManagerV3, u32)>,
+    pub text_input: Option<zwp_text_input_v3::ZwpTextInputV3>,
+
+    /// 获得文字输入焦点的 surface 索引
+    pub focused: Option<usize>,
+    pub enabled: bool,
+    /// 最近一次发送的光标位置，未变化时不重复提交
+    cursor_rect: Option<(i32, i32, i32, i32)>,
+    /// 已发送的 commit 请求数，与 done 事件的 serial 比较
+    commit_count: u32,
+
+    /// 在 done 事件中一起生效的预编辑和提交文字
+    pub pending_preedit: Option<String>,
+    pub pending_commit: Option<String>,
+}
+
+impl TextInputHelper {
+    /// seat 和 manager 都绑定后创建 text_input
+    pub fn init(&mut self, seat: Option<&wl_seat::WlSeat>, qh: &QueueHandle<FoamShot>) {
+        if self.text_input.is_some() {
+            return;
+        }
+        if let (Some((manager, _)), Some(seat)) = (self.manager.as_ref(), seat) {
+            self.text_input = Some(manager.get_text_input(seat, qh, ()));
+        }
+    }
+
+    /// 开始输入，cursor_rect 为光标在焦点 surface 上的位置
+    pub fn enable(&mut self, cursor_rect: (i32, i32, i32, i32)) {
+        let (Some(text_input), Some(_)) = (self.text_input.as_ref(), self.focused) else {
+            return;
+        };
+        if !self.enabled {
+            text_input.enable();
+            text_input.set_content_type(
+                zwp_text_input_v3::ContentHint::None,
+                zwp_text_input_v3::ContentPurpose::Normal,
+            );
+            self.enabled = true;
+        } else if self.cursor_rect == Some(cursor_rect) {
+            return;
+        }
+        let (x, y, w, h) = cursor_rect;
+        text_input.set_cursor_rectangle(x, y, w, h);
+        text_input.commit();
+        self.cursor_rect = Some(cursor_rect);
+        self.commit_count = self.commit_count.wrapping_add(1);
+    }
+
+    /// done 事件是否对应最近一次 commit，否则只应用文字而不修改输入法状态
+    pub fn is_current(&self, serial: u32) -> bool {
+        serial == self.commit_count
+    }
+
+    /// 失去焦点后输入法状态被重置
+    pub fn leave(&mut self) {
+        self.focused = None;
+        self.enabled = false;
+        self.cursor_rect = None;
+    }
+
+    pub fn disable(&mut self) {
+        if let Some(text_input) = self.text_input.as_ref()
+            && self.enabled
+        {
+            text_input.disable();
+            text_input.commit();
+            self.commit_count = self.commit_count.wrapping_add(1);
+        }
+        self.enabled = false;
+        self.cursor_rect = None;
+        self.pending_preedit = None;
+        self.pending_commit = None;
+    }
+}
@@ -0,0 +1,230 @@
+use cairo::Context;
+
+use crate::annotation::{Annotation, Annotator, Style, Tool};
+
+const ITEM_SIZE: f64 = 28.0;
+const ITEM_GAP: f64 = 4.0;
+/// 工具栏与选区之间的距离
+const MARGIN: f64 = 8.0;
+
+/// 工具栏中可选的颜色
+pub const COLORS: [(f64, f64, f64); 6] = [
+    (1.0, 0.0, 0.0),
+    (1.0, 0.6, 0.0),
+    (1.0, 0.9, 0.0),
+    (0.1, 0.7, 0.2),
+    (0.1, 0.4, 1.0),
+    (0.0, 0.0, 0.0),
+];
+/// 工具栏中可选的线宽
+pub const WIDTHS: [f64; 3] = [2.0, 4.0, 8.0];
+
+/// 工具栏中的按钮
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToolbarItem {
+    Tool(Tool),
+    Color((f64, f64, f64)),
+    Width(f64),
+}
+
+impl ToolbarItem {
+    /// 点击按钮后更新标注状态，再次点击当前工具时取消选择
+    ///
+    /// 有选中的标注时颜色和线宽同时应用到该标注，选择工具时取消选中
+    pub fn apply(self, annotator: &mut Annotator) {
+        let selected = annotator
+            .selected
+            .and_then(|i| annotator.annotations.get_mut(i));
+        match self {
+            ToolbarItem::Tool(tool) => {
+                annotator.tool = (annotator.tool != Some(tool)).then_some(tool);
+                annotator.selected = None;
+            }
+            ToolbarItem::Color(color) => {
+                annotator.style.color = color;
+                if let Some(annotation) = selected {
+                    annotation.style.color = color;
+                }
+            }
+            ToolbarItem::Width(width) => {
+                annotator.style.width = width;
+                if let Some(annotation) = selected {
+                    annotation.style.width = width;
+                }
+            }
+        }
+    }
+
+    fn is_active(self, annotator: &Annotator) -> bool {
+        match self {
+            ToolbarItem::Tool(tool) => annotator.tool == Some(tool),
+            ToolbarItem::Color(color) => annotator.style.color == color,
+            ToolbarItem::Width(width) => annotator.style.width == width,
+        }
+    }
+
+    /// 在 (x, y) 为左上角的按钮内绘制图标
+    fn draw_icon(self, cr: &Context, x: f64, y: f64) {
+        let (cx, cy) = (x + ITEM_SIZE / 2.0, y + ITEM_SIZE / 2.0);
+        match self {
+            ToolbarItem::Tool(tool) => {
+                let (a, b) = (7.0, ITEM_SIZE - 7.0);
+                let style = Style {
+                    color: (1.0, 1.0, 1.0),
+                    width: 2.0,
+                };
+                let mut icon = match tool {
+                    Tool::Text => Annotation::new_text((x + 8.0, y + 3.0), style, "serif", 18.0),
+                    Tool::Step => Annotation::new_step((cx, cy), style, 1),
+                    _ => Annotation::new(tool, (x + a, y + b), style),
+                };
+                match tool {
+                    Tool::Text => {
+                        if let Some(content) = icon.text.as_mut() {
+                            content.text = "T".to_string();
+                        }
+                    }
+                    Tool::Step => {}
+                    Tool::Pen => {
+                        icon.points = vec![
+                            (x + a, y + b),
+                            (x + 11.0, y + 12.0),
+                            (x + 16.0, y + 17.0),
+                            (x + b, y + a),
+                        ];
+                    }
+                    _ => icon.update((x + b, y + a)),
+                }
+                match tool {
+                    // 遮挡工具只会处理工具栏背景，单独绘制图标
+                    Tool::Pixelate => {
+                        cr.set_source_rgb(1.0, 1.0, 1.0);
+                        let cell = (b - a) / 3.0;
+                        for i in 0..3 {
+                            for j in 0..3 {
+                                if (i + j) % 2 == 0 {
+                                    cr.rectangle(
+                                        x + a + cell * i as f64,
+                                        y + a + cell * j as f64,
+                                        cell,
+                                        cell,
+                                    );
+                                }
+                            }
+                        }
+                        cr.fill().unwrap();
+                    }
+                    Tool::Blur => {
+                        cr.set_source_rgba(1.0, 1.0, 1.0, 0.5);
+                        cr.arc(cx, cy, (b - a) / 2.0, 0.0, 2.0 * std::f64::consts::PI);
+                        cr.fill().unwrap();
+                        cr.set_source_rgb(1.0, 1.0, 1.0);
+                        cr.arc(cx, cy, (b - a) / 4.0, 0.0, 2.0 * std::f64::consts::PI);
+                        cr.fill().unwrap();
+                    }
+                    // 正片叠底在深色背景上不可见
+                    Tool::Highlighter => {
+                        cr.set_source_rgba(1.0, 0.9, 0.0, 0.6);
+                        cr.set_line_width(8.0);
+                        cr.move_to(x + a, y + b - 4.0);
+                        cr.line_to(x + b, y + a + 4.0);
+                        cr.stroke().unwrap();
+                    }
+                    _ => icon.draw(cr, (0.0, 0.0)),
+                }
+            }
+            ToolbarItem::Color((r, g, b)) => {
+                cr.set_source_rgb(r, g, b);
+                cr.rectangle(x + 7.0, y + 7.0, ITEM_SIZE - 14.0, ITEM_SIZE - 14.0);
+                cr.fill().unwrap();
+            }
+            ToolbarItem::Width(width) => {
+                cr.set_source_rgb(1.0, 1.0, 1.0);
+                cr.arc(cx, cy, width / 2.0 + 1.0, 0.0, 2.0 * std::f64::consts::PI);
+                cr.fill().unwrap();
+            }
+        }
+    }
+}
+
+/// NOTE: 编辑模式下显示在选区下方的标注工具栏
+#[derive(Debug, Clone)]
+pub struct Toolbar {
+    /// 工具栏左上角的全局坐标
+    pub x: f64,
+    pub y: f64,
+    pub items: Vec<ToolbarItem>,
+}
+
+impl Toolbar {
+    /// 放在选区下方，超出 bottom 时放到选区内部底部
+    pub fn new(rect: (i32, i32, i32, i32), bottom: i32) -> Self {
+        let (sx, sy, _, ey) = rect;
+        let items: Vec<ToolbarItem> = Tool::ALL
+            .into_iter()
+            .map(ToolbarItem::Tool)
+            .chain(COLORS.into_iter().map(ToolbarItem::Color))
+            .chain(WIDTHS.into_iter().map(ToolbarItem::Width))
+            .collect();
+
+        let below = ey as f64 + MARGIN;
+        let y = if below + ITEM_SIZE <= bottom as f64 {
+            below
+        } else {
+            (ey as f64 - MARGIN - ITEM_SIZE).max(sy as f64)
+        };
+        Self {
+            x: sx as f64,
+            y,
+            items,
+        }
+    }
+
+    fn item_x(&self, index: usize) -> f64 {
+        self.x + index as f64 * (ITEM_SIZE + ITEM_GAP)
+    }
+
+    fn width(&self) -> f64 {
+        self.items.len() as f64 * (ITEM_SIZE + ITEM_GAP) - ITEM_GAP
+    }
+
+    /// 返回全局坐标处的按钮
+    pub fn item_at(&self, gx: f64, gy: f64) -> Option<ToolbarItem> {
+        if gx < self.x || gy < self.y || gy > self.y + ITEM_SIZE {
+            return None;
+        }
+        let index = ((gx - self.x) / (ITEM_SIZE + ITEM_GAP)) as usize;
+        self.items.get(index).copied()
+    }
+
+    /// 是否在工具栏范围内
+    pub fn contains(&self, gx: f64, gy: f64) -> bool {
+        gx >= self.x && gx <= self.x + self.width() && gy >= self.y && gy <= self.y + ITEM_SIZE
+    }
+
+    /// 在输出的 cairo 上下文中绘制，origin 为输出左上角的全局坐标
+    pub fn draw(&self, cr: &Context, origin: (i32, i32), annotator: &Annotator) {
+        let y = self.y - origin.1 as f64;
+
+        cr.save().unwrap();
+        cr.set_source_rgba(0.15, 0.15, 0.15, 0.95);
+        cr.rectangle(
+            self.x - origin.0 as f64 - ITEM_GAP,
+            y - ITEM_GAP,
+            self.width() + ITEM_GAP * 2.0,
+            ITEM_SIZE + ITEM_GAP * 2.0,
+        );
+        cr.fill().unwrap();
+
+        for (i, item) in self.items.iter().enumerate() {
+            let x = self.item_x(i) - origin.0 as f64;
+            if item.is_active(annotator) {
+                cr.set_source_rgba(0.3, 0.5, 0.9, 1.0);
+                cr.rectangle(x, y, ITEM_SIZE, ITEM_SIZE);
+                cr.fill().unwrap();
+            }
+            item.draw_icon(cr, x, y);
+        }
+        cr.restore().unwrap();
+    }
+}
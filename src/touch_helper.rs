@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use wayland_client::protocol::wl_touch;
+
+use crate::select_rect::SelectRect;
+
+/// 双指缩放开始时的状态
+#[derive(Debug, Clone)]
+pub struct Pinch {
+    pub start_distance: f64,
+    pub start_rect: SelectRect,
+}
+
+#[derive(Default)]
+pub struct TouchHelper {
+    pub touch: Option<wl_touch::WlTouch>,
+
+    /// 各触点的全局坐标
+    pub points: HashMap<i32, (f64, f64)>,
+    /// 各触点按下时所在的 surface 索引，motion 事件不携带 surface
+    pub surfaces: HashMap<i32, usize>,
+    /// 第一个按下的触点，映射为鼠标左键
+    pub primary: Option<i32>,
+    pub pinch: Option<Pinch>,
+}
+
+impl TouchHelper {
+    pub fn is_touching(&self) -> bool {
+        !self.points.is_empty()
+    }
+
+    /// 前两个触点之间的距离
+    pub fn distance(&self) -> Option<f64> {
+        let mut iter = self.points.values();
+        let (a, b) = (iter.next()?, iter.next()?);
+        Some((a.0 - b.0).hypot(a.1 - b.1))
+    }
+
+    pub fn reset(&mut self) {
+        self.points.clear();
+        self.surfaces.clear();
+        self.primary = None;
+        self.pinch = None;
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{annotation::Annotation, select_rect::SelectRect};
+
+/// 编辑模式下可以撤销的状态，选区和标注共用一个撤销栈
+#[derive(Debug, Clone)]
+pub struct EditState {
+    pub rect: Option<SelectRect>,
+    pub annotations: Vec<Annotation>,
+}
+
+impl EditState {
+    /// 忽略选区的拖动缓存
+    pub fn same_as(&self, other: &EditState) -> bool {
+        let same_rect = match (self.rect.as_ref(), other.rect.as_ref()) {
+            (Some(a), Some(b)) => a.same_area(b),
+            (None, None) => true,
+            _ => false,
+        };
+        same_rect && self.annotations == other.annotations
+    }
+}
+
+/// NOTE: 撤销/重做栈，每次完整的按下-松开操作记录一次
+#[derive(Debug)]
+pub struct UndoStack<T> {
+    undo: Vec<T>,
+    redo: Vec<T>,
+    /// 最多保留的撤销记录
+    limit: usize,
+}
+
+impl<T> Default for UndoStack<T> {
+    fn default() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            limit: 100,
+        }
+    }
+}
+
+impl<T> UndoStack<T> {
+    /// 记录操作前的状态，新的操作会清空重做记录
+    pub fn push(&mut self, state: T) {
+        if self.undo.len() >= self.limit {
+            self.undo.remove(0);
+        }
+        self.undo.push(state);
+        self.redo.clear();
+    }
+
+    /// 撤销，返回需要恢复的状态，current 进入重做栈
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let state = self.undo.pop()?;
+        self.redo.push(current);
+        Some(state)
+    }
+
+    /// 重做，返回需要恢复的状态，current 进入撤销栈
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let state = self.redo.pop()?;
+        self.undo.push(current);
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_at_most_limit_entries() {
+        let mut stack = UndoStack::default();
+        for state in 0..150 {
+            stack.push(state);
+        }
+        let mut current = 150;
+        let mut restored = Vec::new();
+        while let Some(state) = stack.undo(current) {
+            restored.push(state);
+            current = state;
+        }
+        // 只剩最近的 100 条，最早的 50 条被丢弃
+        assert_eq!(restored.len(), 100);
+        assert_eq!(restored.first(), Some(&149));
+        assert_eq!(restored.last(), Some(&50));
+    }
+
+    #[test]
+    fn push_clears_redo() {
+        let mut stack = UndoStack::default();
+        stack.push(0);
+        stack.push(1);
+        assert_eq!(stack.undo(2), Some(1));
+        stack.push(1);
+        assert_eq!(stack.redo(3), None);
+    }
+
+    #[test]
+    fn undo_redo_round_trip() {
+        let mut stack = UndoStack::default();
+        stack.push(0);
+        stack.push(1);
+        // 当前状态为 2
+        assert_eq!(stack.undo(2), Some(1));
+        assert_eq!(stack.undo(1), Some(0));
+        assert_eq!(stack.undo(0), None);
+        assert_eq!(stack.redo(0), Some(1));
+        assert_eq!(stack.redo(1), Some(2));
+        assert_eq!(stack.redo(2), None);
+        assert_eq!(stack.undo(2), Some(1));
+    }
+}
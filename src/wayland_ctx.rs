@@ -1,8 +1,10 @@
-use log::{debug, error};
+use std::path::PathBuf;
+
+use log::{debug, error, warn};
 use smithay_client_toolkit::shm::{self};
 use wayland_client::{
     QueueHandle,
-    protocol::{wl_compositor, wl_keyboard, wl_pointer, wl_seat},
+    protocol::{wl_compositor, wl_pointer, wl_seat},
 };
 use wayland_protocols::{
     wp::{
@@ -14,8 +16,27 @@ use wayland_protocols::{
 use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1;
 
 use crate::{
-    config, foam_outputs, foamshot::FoamShot, pointer_helper::PointerHelper,
-    select_rect::SelectRect, zwlr_screencopy_mode,
+    action::EditAction,
+    action_bar::ActionBar,
+    annotation::{Annotator, Style},
+    config::{self, ClickTarget},
+    context_menu::{ContextMenu, MenuItem},
+    drag_helper::DragHelper,
+    foam_outputs::{self, Overlay},
+    foamshot::FoamShot,
+    keyboard_helper::KeyboardHelper,
+    measure::{self, MeasureKind, MeasureState, Measurement},
+    pin::PinWindow,
+    pointer_helper::PointerHelper,
+    save_helper::{self, SavedImage},
+    select_rect::{SelectRect, SnapTargets, SubRect},
+    state::LastState,
+    text_input_helper::TextInputHelper,
+    toolbar::{Toolbar, ToolbarItem},
+    touch_helper::TouchHelper,
+    undo_stack::{EditState, UndoStack},
+    window_info::{self, WindowInfo},
+    zwlr_screencopy_mode,
 };
 
 #[derive(Default)]
@@ -24,7 +45,6 @@ pub struct WaylandCtx {
     /// u32 is wl_registry name
     pub compositor: Option<(wl_compositor::WlCompositor, u32)>,
     pub seat: Option<(wl_seat::WlSeat, u32)>,
-    pub keyboard: Option<wl_keyboard::WlKeyboard>,
     pub qh: Option<QueueHandle<FoamShot>>,
     pub shm: Option<shm::Shm>,
     // pub pool: Option<slot::SlotPool>,
@@ -46,21 +66,79 @@ pub struct WaylandCtx {
 
     /// 光标管理器
     pub pointer_helper: PointerHelper,
+    /// 键盘及修饰键状态
+    pub keyboard_helper: KeyboardHelper,
+    /// 触摸状态
+    pub touch_helper: TouchHelper,
+    /// 输入法状态
+    pub text_input_helper: TextInputHelper,
 
     pub config: config::FoamConfig,
     pub scm: zwlr_screencopy_mode::ZwlrScreencopyMode,
     pub global_rect: Option<SelectRect>,
+    /// 已提交的选区，保存时与当前选区一起输出
+    pub committed_rects: Vec<SelectRect>,
+    /// 编辑模式下的右键菜单
+    pub context_menu: Option<ContextMenu>,
+
+    /// 另存为时正在输入的文件名
+    pub rename: Option<String>,
+    /// 另存为确认后的输出路径，保存时替换配置中的路径
+    pub save_as: Option<PathBuf>,
+
+    /// 编辑选区和标注的撤销记录
+    pub undo_stack: UndoStack<EditState>,
+    /// 当前编辑操作开始前的状态，松开时与结果比较
+    pub edit_snapshot: Option<EditState>,
+
+    /// 单击截取窗口时的窗口信息
+    pub window_info: Option<WindowInfo>,
+
+    /// 本次保存写入的文件
+    pub saved_images: Vec<SavedImage>,
+
+    /// 编辑模式下的标注
+    pub annotator: Annotator,
+
+    /// 测量模式下的测量结果
+    pub measure: Option<MeasureState>,
+
+    /// 上一次会话保存的状态
+    pub last_state: LastState,
+
+    /// 钉在屏幕上的截图，存在时选区遮罩层已销毁
+    pub pin: Option<PinWindow>,
+
+    /// 把截图拖放到其他程序
+    pub drag_helper: DragHelper,
 }
 
 impl WaylandCtx {
     pub fn new(shm: shm::Shm, qh: QueueHandle<FoamShot>) -> Self {
         let config = config::FoamConfig::new();
+        let last_state = LastState::load();
+        let step_start = match config.step_start {
+            Some(start) => start,
+            None if config.continue_steps => last_state.next_step.unwrap_or(1),
+            None => 1,
+        };
         Self {
             qh: Some(qh),
             shm: Some(shm),
             foam_outputs: Some(Vec::new()),
             config: config::FoamConfig::new(),
             current_freeze: config.freeze,
+            annotator: Annotator::new(
+                Style {
+                    color: config.stroke_color,
+                    width: config.stroke_width,
+                },
+                config.font_family.clone(),
+                config.font_size,
+                step_start,
+            ),
+            measure: (config.mode == config::Mode::Measure).then(MeasureState::default),
+            last_state,
             ..Default::default()
         }
     }
@@ -115,6 +193,8 @@ impl WaylandCtx {
     /// 用一个空的buffer附加到surface，使屏幕恢复正常状态，用来 toggle freeze 前清空屏幕以便进行copy
     pub fn unset_freeze(&mut self) {
         for (_i, v) in self.foam_outputs.as_mut().unwrap().iter_mut().enumerate() {
+            // 只能放大冻结的画面
+            v.zoom = None;
             v.clean_attach();
         }
     }
@@ -157,77 +237,796 @@ impl WaylandCtx {
         let (end_gx, end_gy) = (end_x as i32, end_y as i32);
 
         // 计算父矩形边界
-        let rect = SelectRect::new(
+        let mut rect = SelectRect::new(
             start_gx.min(end_gx),
             start_gy.min(end_gy),
             start_gx.max(end_gx),
             start_gy.max(end_gy),
         );
 
+        // 只吸附跟随光标移动的角
+        let moving_corner = match (end_gx >= start_gx, end_gy >= start_gy) {
+            (true, true) => EditAction::BottomRight,
+            (true, false) => EditAction::TopRight,
+            (false, true) => EditAction::BottomLeft,
+            (false, false) => EditAction::TopLeft,
+        };
+        rect.snap(moving_corner, &self.snap_targets(), self.snap_threshold());
+
         self.global_rect = Some(rect);
     }
+
+    /// 编辑选区时边/角的命中范围，触摸时更大
+    pub fn hit_threshold(&self) -> i32 {
+        if self.touch_helper.is_touching() {
+            30
+        } else {
+            15
+        }
+    }
+
+    /// 当前生效的磁吸距离，按住 Alt 时临时关闭
+    pub fn snap_threshold(&self) -> i32 {
+        if self.keyboard_helper.alt() {
+            0
+        } else {
+            self.config.snap_threshold
+        }
+    }
+
+    /// 收集磁吸目标：所有输出的边界以及上一次保存的选区
+    pub fn snap_targets(&self) -> SnapTargets {
+        let mut targets = SnapTargets::default();
+        for output in self.foam_outputs.as_ref().unwrap() {
+            let (width, height) = output.logical_size();
+            targets.push_rect(
+                output.global_x,
+                output.global_y,
+                output.global_x + width,
+                output.global_y + height,
+            );
+        }
+        if let Some(last) = self.last_state.last_rect() {
+            targets.push_rect(last.sx, last.sy, last.ex, last.ey);
+        }
+        targets
+    }
+
+    /// 对按下时的起点进行磁吸
+    pub fn snap_start_pos(&mut self) {
+        let threshold = self.snap_threshold();
+        if threshold <= 0 {
+            return;
+        }
+        let targets = self.snap_targets();
+        if let Some((x, y)) = self.pointer_helper.g_start_pos {
+            self.pointer_helper.g_start_pos = Some((
+                targets.snap_x(x as i32, threshold) as f64,
+                targets.snap_y(y as i32, threshold) as f64,
+            ));
+        }
+    }
+
+    /// 编辑选区后对正在移动的边进行磁吸
+    pub fn snap_global_rect(&mut self, edge: EditAction) {
+        let threshold = self.snap_threshold();
+        let targets = self.snap_targets();
+        if let Some(rect) = self.global_rect.as_mut() {
+            rect.snap(edge, &targets, threshold);
+        }
+    }
+
+    /// 将上一次保存的选区设为当前选区，返回 None 表示没有记录
+    /// 返回值表示输出布局是否与记录时一致
+    pub fn restore_last_rect(&mut self) -> Option<bool> {
+        let rect = self.last_state.last_rect()?;
+        let layout_matches = self
+            .last_state
+            .layout_matches(self.foam_outputs.as_ref().unwrap());
+        self.global_rect = Some(rect);
+        self.process_subrects_and_send();
+        Some(layout_matches)
+    }
+
+    /// 保存成功后记录本次选区，供下次磁吸使用
+    pub fn remember_global_rect(&mut self) {
+        let Some(rect) = self.global_rect.as_ref() else {
+            return;
+        };
+        self.last_state
+            .set_rect(rect, self.foam_outputs.as_ref().unwrap());
+        // 只有使用了步骤序号时才更新，供 --continue-steps 使用
+        if self.annotator.has_steps() {
+            self.last_state.next_step = Some(self.annotator.next_step);
+        }
+        if let Err(e) = self.last_state.save() {
+            warn!("save last state error: {}", e);
+        }
+    }
     pub fn process_subrects_and_send(&mut self) {
         let foam_outputs = self.foam_outputs.as_mut().unwrap();
         let rect = self.global_rect.as_ref().unwrap();
 
-        let SelectRect {
-            sx: min_x,
-            sy: min_y,
-            ex: max_x,
-            ey: max_y,
-            ..
-        } = *rect;
-
         for output in foam_outputs {
             // 计算与当前输出的交集区域
-            let intersect_left = output.global_x.max(min_x);
-            let intersect_top = output.global_y.max(min_y);
-            let intersect_right = (output.global_x + output.width).min(max_x);
-            let intersect_bottom = (output.global_y + output.height).min(max_y);
-
-            // 判断有效交集区域
-            if intersect_left < intersect_right && intersect_top < intersect_bottom {
-                // 计算相对于输出的局部坐标
-                let local_x = intersect_left - output.global_x;
-                let local_y = intersect_top - output.global_y;
-                let width = intersect_right - intersect_left;
-                let height = intersect_bottom - intersect_top;
-
+            if let Some(subrect) = output.intersect(rect) {
                 // 更新输出状态
-                output.new_subrect(local_x, local_y, width, height);
+                output.subrect = Some(subrect);
                 output.need_redraw = true;
 
                 // 提交surface更新
-                if let Some(surface) = &mut output.surface {
-                    let qh = self.qh.as_ref().unwrap();
-                    surface.frame(qh, output.id);
-                    surface.set_buffer_scale(output.scale.round() as i32);
-                    surface.commit();
-                }
+                output.request_frame(self.qh.as_ref().unwrap());
             } else {
                 // 清理无效区域
                 output.subrect = None;
                 // TODO: 这里应该不用显示设置为false
                 output.need_redraw = false;
+                // 仍有已提交的选区时需要重绘，清除残留的当前选区
+                if !output.committed_subrects.is_empty() {
+                    output.need_redraw = true;
+                    output.request_frame(self.qh.as_ref().unwrap());
+                }
             }
         }
     }
 
+    /// 重绘所有输出
+    pub fn redraw_all(&mut self) {
+        let qh = self.qh.as_ref().unwrap();
+        for output in self.foam_outputs.as_mut().unwrap() {
+            output.need_redraw = true;
+            output.request_frame(qh);
+        }
+    }
+
+    /// 取消正在绘制的选区，保留已提交的选区
+    pub fn cancel_global_rect(&mut self) {
+        self.global_rect = None;
+        for output in self.foam_outputs.as_mut().unwrap() {
+            output.subrect = None;
+        }
+        self.redraw_all();
+    }
+
+    /// 在当前光标位置打开右键菜单
+    pub fn open_context_menu(&mut self) {
+        let (Some(index), Some((x, y))) = (self.current_index, self.pointer_helper.g_current_pos)
+        else {
+            return;
+        };
+        self.context_menu = Some(ContextMenu::new(index, x, y));
+        self.redraw_all();
+    }
+
+    pub fn close_context_menu(&mut self) {
+        if self.context_menu.take().is_some() {
+            self.redraw_all();
+        }
+    }
+
+    /// 提交当前选区，之后可以继续添加新的选区
+    pub fn commit_global_rect(&mut self) {
+        let Some(rect) = self.global_rect.take() else {
+            return;
+        };
+        let qh = self.qh.as_ref().unwrap();
+        for output in self.foam_outputs.as_mut().unwrap() {
+            if let Some(subrect) = output.intersect(&rect) {
+                output.committed_subrects.push(subrect);
+            }
+            output.subrect = None;
+            output.need_redraw = true;
+            output.request_frame(qh);
+        }
+        self.committed_rects.push(rect);
+    }
+
+    /// 已提交的选区与当前选区，每个选区对应各输出上的子矩形
+    pub fn region_subrects(&self) -> Vec<Vec<SubRect>> {
+        let outputs = self.foam_outputs.as_ref().unwrap();
+        let mut regions: Vec<Vec<SubRect>> = self
+            .committed_rects
+            .iter()
+            .map(|rect| outputs.iter().filter_map(|o| o.intersect(rect)).collect())
+            .collect();
+
+        // 当前选区直接取自各输出，包含全屏截图设置的子矩形
+        let current: Vec<SubRect> = outputs.iter().filter_map(|o| o.subrect.clone()).collect();
+        if !current.is_empty() {
+            regions.push(current);
+        }
+        regions.retain(|r| !r.is_empty());
+        regions
+    }
+
+    /// 滚轮在选区内滚动时以中心为基准扩大或缩小选区
+    pub fn grow_global_rect(&mut self, delta: i32) {
+        let (Some(rect), Some((x, y))) =
+            (self.global_rect.as_ref(), self.pointer_helper.g_current_pos)
+        else {
+            return;
+        };
+        if !rect.contains(x as i32, y as i32) {
+            return;
+        }
+        self.begin_edit_gesture();
+        if let Some(rect) = self.global_rect.as_mut() {
+            rect.grow(delta);
+        }
+        self.end_edit_gesture();
+        self.process_subrects_and_send();
+    }
+
+    /// 以光标位置为中心放大或缩小光标所在输出的冻结画面
+    pub fn zoom_at_pointer(&mut self, steps: i32) {
+        if !self.current_freeze {
+            return;
+        }
+        let (Some(index), Some((x, y))) = (self.unknown_index, self.pointer_helper.g_current_pos)
+        else {
+            return;
+        };
+        let Some(output) = self.foam_outputs.as_mut().unwrap().get_mut(index) else {
+            return;
+        };
+        output.zoom_at(
+            x - output.global_x as f64,
+            y - output.global_y as f64,
+            steps,
+        );
+        output.need_redraw = true;
+        output.request_frame(self.qh.as_ref().unwrap());
+    }
+
+    fn edit_state(&self) -> EditState {
+        EditState {
+            rect: self.global_rect.clone(),
+            annotations: self.annotator.annotations.clone(),
+        }
+    }
+
+    /// 编辑操作开始时记录选区和标注
+    pub fn begin_edit_gesture(&mut self) {
+        self.edit_snapshot = Some(self.edit_state());
+    }
+
+    /// 编辑操作结束，选区或标注有变化时记录一次撤销
+    pub fn end_edit_gesture(&mut self) {
+        let Some(before) = self.edit_snapshot.take() else {
+            return;
+        };
+        if !before.same_as(&self.edit_state()) {
+            self.undo_stack.push(before);
+        }
+    }
+
+    /// 撤销或重做选区和标注的编辑
+    pub fn undo_edit(&mut self, redo: bool) {
+        let current = self.edit_state();
+        let restored = if redo {
+            self.undo_stack.redo(current)
+        } else {
+            self.undo_stack.undo(current)
+        };
+        if let Some(state) = restored {
+            if state.rect.is_some() {
+                self.global_rect = state.rect;
+            }
+            self.annotator.restore(state.annotations);
+            self.process_subrects_and_send();
+            self.redraw_all();
+        }
+    }
+
+    /// 全局逻辑坐标所在的输出
+    pub fn output_at(&self, gx: i32, gy: i32) -> Option<&foam_outputs::FoamOutput> {
+        self.foam_outputs.as_ref()?.iter().find(|o| {
+            let (lw, lh) = o.logical_size();
+            gx >= o.global_x && gy >= o.global_y && gx < o.global_x + lw && gy < o.global_y + lh
+        })
+    }
+
+    /// 销毁所有输出上的遮罩层
+    pub fn destroy_overlays(&mut self) {
+        for output in self.foam_outputs.as_mut().unwrap().iter_mut() {
+            if let Some(layer_surface) = output.layer_surface.take() {
+                layer_surface.destroy();
+            }
+            if let Some(surface) = output.surface.take() {
+                surface.destroy();
+            }
+        }
+    }
+
+    /// 渲染截图并钉在选区所在的输出上，同时销毁所有遮罩层
+    pub fn start_pin(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let image = save_helper::render_regions(self)?
+            .into_iter()
+            .next()
+            .ok_or("未找到有效截图区域")?
+            .surface;
+        let rect = self.global_rect.as_ref().ok_or("未选择截图区域")?;
+        let (sx, sy) = (rect.sx.min(rect.ex), rect.sy.min(rect.ey));
+
+        let output = self
+            .output_at(sx, sy)
+            .or_else(|| self.foam_outputs.as_ref()?.first())
+            .ok_or("输出设备未初始化")?;
+        let wl_output = output.output.clone().ok_or("输出设备未初始化")?;
+        let bounds = output.logical_size();
+        let position = (
+            (sx - output.global_x).clamp(0, bounds.0),
+            (sy - output.global_y).clamp(0, bounds.1),
+        );
+        let scale = output.scale.round() as i32;
+
+        self.destroy_overlays();
+
+        self.pin = Some(PinWindow::new(
+            &self.compositor.as_ref().ok_or("compositor 未绑定")?.0,
+            &self.layer_shell.as_ref().ok_or("layer shell 未绑定")?.0,
+            self.shm.as_ref().unwrap(),
+            self.qh.as_ref().unwrap(),
+            &wl_output,
+            bounds,
+            scale,
+            position,
+            image,
+            self.config.pin_opacity,
+        )?);
+        Ok(())
+    }
+
+    /// 编辑模式下的标注工具栏，没有选区时不显示
+    pub fn toolbar(&self) -> Option<Toolbar> {
+        if !self.config.edit {
+            return None;
+        }
+        let rect = self.global_rect.as_ref()?;
+        let bottom = self
+            .output_at(rect.sx, rect.ey - 1)
+            .map_or(rect.ey, |o| o.global_y + o.logical_size().1);
+        Some(Toolbar::new((rect.sx, rect.sy, rect.ex, rect.ey), bottom))
+    }
+
+    /// 编辑模式下选区上方的操作栏，需要 --action-bar
+    pub fn action_bar(&self) -> Option<ActionBar> {
+        if !self.config.edit || !self.config.action_bar {
+            return None;
+        }
+        let rect = self.global_rect.as_ref()?;
+        let top = self
+            .output_at(rect.ex - 1, rect.sy)
+            .map_or(rect.sy, |o| o.global_y);
+        Some(ActionBar::new(
+            (rect.sx, rect.sy, rect.ex, rect.ey),
+            top,
+            self.rename.clone(),
+        ))
+    }
+
+    /// 光标处的操作栏按钮，Some(None) 表示在操作栏空白处
+    pub fn action_bar_item_at_pointer(&self) -> Option<Option<MenuItem>> {
+        let (x, y) = self.pointer_helper.g_current_pos?;
+        let action_bar = self.action_bar()?;
+        action_bar.contains(x, y).then(|| action_bar.item_at(x, y))
+    }
+
+    /// 开始输入另存为的文件名，初始为当前的文件名
+    pub fn begin_rename(&mut self) {
+        let name = self
+            .config
+            .output_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.rename = Some(name);
+        self.redraw_all();
+    }
+
+    /// 在文件名末尾输入文字，不允许输入路径分隔符
+    pub fn insert_rename_text(&mut self, input: &str) {
+        if let Some(name) = self.rename.as_mut() {
+            name.extend(input.chars().filter(|c| *c != '/' && !c.is_control()));
+            self.redraw_all();
+        }
+    }
+
+    pub fn delete_rename_char(&mut self) {
+        if let Some(name) = self.rename.as_mut() {
+            name.pop();
+            self.redraw_all();
+        }
+    }
+
+    /// 结束输入文件名，confirm 时记录新的输出路径，返回是否可以保存
+    pub fn finish_rename(&mut self, confirm: bool) -> bool {
+        let name = self.rename.take();
+        self.redraw_all();
+        let Some(name) = name.filter(|name| confirm && !name.trim().is_empty()) else {
+            return false;
+        };
+        let dir = self
+            .config
+            .output_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default();
+        self.save_as = Some(dir.join(name.trim()));
+        true
+    }
+
+    /// 光标处的工具栏按钮，Some(None) 表示在工具栏空白处
+    pub fn toolbar_item_at_pointer(&self) -> Option<Option<ToolbarItem>> {
+        let (x, y) = self.pointer_helper.g_current_pos?;
+        let toolbar = self.toolbar()?;
+        toolbar.contains(x, y).then(|| toolbar.item_at(x, y))
+    }
+
+    /// 在光标位置开始绘制标注
+    pub fn begin_annotation(&mut self) {
+        if let Some(pos) = self.pointer_helper.g_current_pos {
+            self.begin_edit_gesture();
+            self.annotator.selected = None;
+            self.annotator.begin(pos);
+            self.redraw_all();
+        }
+    }
+
+    pub fn update_annotation(&mut self) {
+        if let Some(pos) = self.pointer_helper.g_current_pos {
+            self.annotator.update(pos);
+            self.redraw_all();
+        }
+    }
+
+    pub fn finish_annotation(&mut self) {
+        self.annotator.finish();
+        self.end_edit_gesture();
+        self.redraw_all();
+    }
+
+    /// 光标处的标注及编辑行为，没有选中工具时才能选中标注
+    pub fn object_at_pointer(&self) -> Option<(usize, EditAction)> {
+        if self.annotator.tool.is_some() {
+            return None;
+        }
+        let pos = self.pointer_helper.g_current_pos?;
+        self.annotator.object_at(pos, self.hit_threshold())
+    }
+
+    /// 选中光标处的标注并开始拖动
+    pub fn begin_object_drag(&mut self, index: usize, handle: EditAction) {
+        if let Some(pos) = self.pointer_helper.g_current_pos {
+            self.begin_edit_gesture();
+            self.annotator.begin_drag(index, handle, pos);
+            self.redraw_all();
+        }
+    }
+
+    pub fn update_object_drag(&mut self) {
+        if let Some(pos) = self.pointer_helper.g_current_pos {
+            self.annotator.update_drag(pos);
+            self.redraw_all();
+        }
+    }
+
+    pub fn finish_object_drag(&mut self) {
+        self.annotator.finish_drag();
+        self.end_edit_gesture();
+        self.redraw_all();
+    }
+
+    /// 取消选中标注
+    pub fn deselect_object(&mut self) {
+        if self.annotator.selected.take().is_some() {
+            self.redraw_all();
+        }
+    }
+
+    /// 删除选中的标注
+    pub fn delete_selected_object(&mut self) {
+        self.begin_edit_gesture();
+        if self.annotator.delete_selected() {
+            self.redraw_all();
+        }
+        self.end_edit_gesture();
+    }
+
+    /// 点击工具栏按钮，有选中的标注时同时修改它的颜色或线宽
+    pub fn apply_toolbar_item(&mut self, item: ToolbarItem) {
+        self.begin_edit_gesture();
+        item.apply(&mut self.annotator);
+        self.end_edit_gesture();
+        self.redraw_all();
+    }
+
+    /// seat 和输入法管理器都绑定后创建 text_input
+    pub fn init_text_input(&mut self) {
+        let seat = self.seat.as_ref().map(|(seat, _)| seat);
+        self.text_input_helper.init(seat, self.qh.as_ref().unwrap());
+    }
+
+    /// seat 和拖放管理器都绑定后创建 data_device
+    pub fn init_data_device(&mut self) {
+        let seat = self.seat.as_ref().map(|(seat, _)| seat);
+        self.drag_helper.init(seat, self.qh.as_ref().unwrap());
+    }
+
+    /// 开始把截图拖放到其他程序，拖动的图片与钉图或保存的图片相同
+    ///
+    /// 编辑模式下会隐藏遮罩层，让光标下的程序能够接收拖放
+    pub fn start_drag(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let origin = match self.pin.as_ref() {
+            Some(pin) => pin.surface.clone(),
+            None => self
+                .unknown_index
+                .and_then(|i| self.foam_outputs.as_ref()?.get(i)?.surface.clone())
+                .ok_or("光标不在任何输出上")?,
+        };
+        let region = save_helper::render_regions(self)?
+            .into_iter()
+            .next()
+            .ok_or("未找到有效截图区域")?;
+        let file = self.drag_file(&region);
+
+        self.drag_helper.start(
+            &self.compositor.as_ref().ok_or("compositor 未绑定")?.0,
+            self.shm.as_ref().unwrap(),
+            self.qh.as_ref().unwrap(),
+            &origin,
+            self.pointer_helper.button_serial,
+            &region.surface,
+            file.as_deref(),
+        )?;
+
+        if self.pin.is_none() {
+            for output in self.foam_outputs.as_mut().unwrap() {
+                if let Some(surface) = output.surface.as_ref() {
+                    surface.attach(None, 0, 0);
+                    surface.commit();
+                    output.hidden = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 拖放被取消，删除为拖放写入的文件，并重新显示隐藏的遮罩层
+    pub fn cancel_drag(&mut self) {
+        if let Some(path) = self.drag_helper.created_file.take() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("删除拖放文件失败: {}", e);
+            }
+            self.saved_images.retain(|image| image.path != path);
+        }
+        // 空 buffer 使 layer surface 取消映射，重新提交以获得新的 configure
+        for output in self.foam_outputs.as_ref().unwrap() {
+            if let Some(surface) = output.surface.as_ref().filter(|_| output.hidden) {
+                surface.commit();
+            }
+        }
+    }
+
+    /// uri-list 指向的文件，还没有保存时以 PNG 格式写入输出路径
+    fn drag_file(&mut self, region: &save_helper::RenderedRegion) -> Option<PathBuf> {
+        if self.saved_images.is_empty() {
+            let path = self.config.output_path.with_extension("png");
+            let saved = SavedImage::new(path.clone(), config::ImageType::Png, region);
+            let result = save_helper::encode_png(self, &saved, &region.surface)
+                .map_err(|e| e.to_string())
+                .and_then(|png_data| {
+                    std::fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)
+                        .and_then(|mut file| std::io::Write::write_all(&mut file, &png_data))
+                        .map_err(|e| e.to_string())
+                });
+            if let Err(e) = result {
+                warn!("保存拖放文件失败: {}", e);
+                return None;
+            }
+            self.saved_images.push(saved);
+            self.drag_helper.created_file = Some(path);
+        }
+        self.saved_images.first()?.path.canonicalize().ok()
+    }
+
+    /// 在光标位置放置文字框并开始输入
+    pub fn begin_text(&mut self) {
+        if let Some(pos) = self.pointer_helper.g_current_pos {
+            self.begin_edit_gesture();
+            self.annotator.selected = None;
+            self.annotator.begin_text(pos);
+            self.enable_text_input();
+            self.redraw_all();
+        }
+    }
+
+    /// 启用输入法，并更新候选框位置
+    pub fn enable_text_input(&mut self) {
+        let (Some((x, y, h)), Some(index)) =
+            (self.annotator.text_caret(), self.text_input_helper.focused)
+        else {
+            return;
+        };
+        let Some(output) = self.foam_outputs.as_ref().unwrap().get(index) else {
+            return;
+        };
+        let rect = (
+            x as i32 - output.global_x,
+            y as i32 - output.global_y,
+            1,
+            h.ceil() as i32,
+        );
+        self.text_input_helper.enable(rect);
+    }
+
+    /// 在 done 事件中应用输入法提交的文字和预编辑文字
+    ///
+    /// serial 落后于已发送的 commit 时不更新候选框位置，等待最新 commit 的 done
+    pub fn apply_text_input(&mut self, serial: u32) {
+        let commit = self.text_input_helper.pending_commit.take();
+        let preedit = self.text_input_helper.pending_preedit.take();
+        if let (Some(commit), Some(text)) = (commit, self.annotator.editing_text()) {
+            text.push_str(&commit);
+        }
+        self.annotator.preedit = preedit.unwrap_or_default();
+        if self.text_input_helper.is_current(serial) {
+            self.enable_text_input();
+        }
+        self.redraw_all();
+    }
+
+    /// 没有输入法时直接插入按键对应的文字
+    pub fn insert_text(&mut self, input: &str) {
+        if let Some(text) = self.annotator.editing_text() {
+            text.push_str(input);
+            self.enable_text_input();
+            self.redraw_all();
+        }
+    }
+
+    pub fn delete_text_char(&mut self) {
+        if let Some(text) = self.annotator.editing_text() {
+            text.pop();
+            self.enable_text_input();
+            self.redraw_all();
+        }
+    }
+
+    /// 结束输入，空的文字框会被丢弃
+    pub fn finish_text(&mut self) {
+        self.text_input_helper.disable();
+        self.finish_annotation();
+    }
+
+    /// 在光标位置开始新的测量
+    pub fn begin_measure(&mut self, kind: MeasureKind) {
+        let Some(pos) = self.pointer_helper.g_current_pos else {
+            return;
+        };
+        let factor = self
+            .output_at(pos.0 as i32, pos.1 as i32)
+            .map_or((1.0, 1.0), |o| o.physical_factor());
+        if let Some(measure) = self.measure.as_mut() {
+            measure.current = Some(Measurement::new(kind, pos, factor));
+        }
+        self.redraw_all();
+    }
+
+    /// 拖动时更新测量终点，未拖动时更新颜色探针
+    pub fn update_measure(&mut self) {
+        let Some(pos) = self.pointer_helper.g_current_pos else {
+            return;
+        };
+        let probe = match self.measure.as_ref() {
+            Some(measure) if measure.current.is_none() => {
+                measure::probe(self, pos.0 as i32, pos.1 as i32)
+            }
+            _ => None,
+        };
+        let Some(measure) = self.measure.as_mut() else {
+            return;
+        };
+        match measure.current.as_mut() {
+            Some(current) => current.end = pos,
+            None => measure.probe = probe,
+        }
+        self.redraw_all();
+    }
+
+    /// 结束当前测量并返回测量结果
+    pub fn finish_measure(&mut self) -> Option<String> {
+        let measure = self.measure.as_mut()?;
+        let current = measure.current.take()?;
+        let label = current.label();
+        measure.measurements.push(current);
+        self.redraw_all();
+        Some(label)
+    }
+
+    /// 取消正在进行的测量，没有时删除最后一次测量
+    pub fn undo_measure(&mut self) {
+        let Some(measure) = self.measure.as_mut() else {
+            return;
+        };
+        if measure.current.take().is_none() {
+            measure.measurements.pop();
+        }
+        self.redraw_all();
+    }
+
+    /// 单击时将选区设为光标下的窗口，无法获取窗口信息时设为光标所在的输出
+    pub fn select_under_pointer(&mut self) {
+        let Some((x, y)) = self.pointer_helper.g_current_pos else {
+            return;
+        };
+        let (gx, gy) = (x as i32, y as i32);
+
+        let window = match self.config.click_target {
+            ClickTarget::Auto => window_info::find_window_at(gx, gy),
+            ClickTarget::Output => None,
+        };
+
+        let rect = match window {
+            Some(window) => {
+                debug!("capture window {} ({})", window.app_id, window.title);
+                let rect = window.to_select_rect();
+                self.window_info = Some(window);
+                rect
+            }
+            None => {
+                let Some(output) = self.output_at(gx, gy) else {
+                    return;
+                };
+                let (width, height) = output.logical_size();
+                SelectRect::new(
+                    output.global_x,
+                    output.global_y,
+                    output.global_x + width,
+                    output.global_y + height,
+                )
+            }
+        };
+
+        self.global_rect = Some(rect);
+        self.process_subrects_and_send();
+    }
+
+    /// 鼠标按下时以当前位置为起点开始绘制选区
+    pub fn begin_draw(&mut self) {
+        self.pointer_helper.start_index = self.current_index;
+        self.pointer_helper.g_start_pos = self.pointer_helper.g_current_pos;
+        self.snap_start_pos();
+        self.generate_rects_and_send_frame();
+    }
+
     /// 在鼠标按下和拖动时候被调用，为每个output生成子矩形，如果成功生成，对应output标记为需要重绘, 且surface将发送帧回调
     pub fn generate_rects_and_send_frame(&mut self) {
         self.compute_global_rect();
         self.process_subrects_and_send();
     }
 
-    /// 在wl_callback中被调用，为需要重绘的输出更新下一帧
-    pub fn update_select_region(&mut self) {
+    /// 在wl_callback中被调用，为需要重绘的输出更新下一帧，editing 为真时显示标注工具栏
+    pub fn update_select_region(&mut self, editing: bool) {
+        let toolbar = if editing { self.toolbar() } else { None };
+        let action_bar = if editing { self.action_bar() } else { None };
         for (i, v) in self.foam_outputs.as_mut().unwrap().iter_mut().enumerate() {
             if !v.need_redraw {
                 continue;
             }
             let base_canvas = self.scm.base_canvas.as_mut().unwrap().get_mut(&i).unwrap();
 
-            v.update_select_subrect(base_canvas, self.current_freeze);
+            let overlay = Overlay {
+                menu: self.context_menu.as_ref().filter(|m| m.output_id == i),
+                measure: self.measure.as_ref(),
+                annotator: Some(&self.annotator),
+                toolbar: toolbar.as_ref(),
+                action_bar: action_bar.as_ref(),
+            };
+            v.update_select_subrect(base_canvas, self.current_freeze, overlay);
         }
     }
 
@@ -0,0 +1,127 @@
+use std::process::Command;
+
+use log::debug;
+use serde_json::Value;
+
+use crate::select_rect::SelectRect;
+
+/// NOTE: 通过合成器 IPC 获取的窗口信息，wayland 本身不提供其他客户端的窗口位置
+#[derive(Debug, Clone, Default)]
+pub struct WindowInfo {
+    pub app_id: String,
+    pub title: String,
+    /// 全局逻辑坐标 (x, y, w, h)
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl WindowInfo {
+    pub fn contains(&self, gx: i32, gy: i32) -> bool {
+        gx >= self.x && gy >= self.y && gx < self.x + self.width && gy < self.y + self.height
+    }
+
+    pub fn to_select_rect(&self) -> SelectRect {
+        SelectRect::new(self.x, self.y, self.x + self.width, self.y + self.height)
+    }
+}
+
+fn run_json(program: &str, args: &[&str]) -> Option<Value> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+fn as_i32(v: &Value) -> i32 {
+    v.as_i64().unwrap_or(0) as i32
+}
+
+/// hyprland: 只考虑当前可见工作区上已映射的窗口
+fn hyprland_windows() -> Option<Vec<WindowInfo>> {
+    std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+
+    let monitors = run_json("hyprctl", &["-j", "monitors"])?;
+    let visible: Vec<i64> = monitors
+        .as_array()?
+        .iter()
+        .filter_map(|m| m["activeWorkspace"]["id"].as_i64())
+        .collect();
+
+    let clients = run_json("hyprctl", &["-j", "clients"])?;
+    let mut windows: Vec<(i64, WindowInfo)> = clients
+        .as_array()?
+        .iter()
+        .filter(|c| c["mapped"].as_bool().unwrap_or(false))
+        .filter(|c| !c["hidden"].as_bool().unwrap_or(false))
+        .filter(|c| {
+            c["workspace"]["id"]
+                .as_i64()
+                .is_some_and(|id| visible.contains(&id))
+        })
+        .map(|c| {
+            (
+                c["focusHistoryID"].as_i64().unwrap_or(i64::MAX),
+                WindowInfo {
+                    app_id: c["class"].as_str().unwrap_or_default().to_string(),
+                    title: c["title"].as_str().unwrap_or_default().to_string(),
+                    x: as_i32(&c["at"][0]),
+                    y: as_i32(&c["at"][1]),
+                    width: as_i32(&c["size"][0]),
+                    height: as_i32(&c["size"][1]),
+                },
+            )
+        })
+        .collect();
+
+    // 最近聚焦的窗口排在前面，近似为堆叠顺序
+    windows.sort_by_key(|(focus, _)| *focus);
+    Some(windows.into_iter().map(|(_, w)| w).collect())
+}
+
+/// sway: 递归遍历树，收集可见的窗口节点
+fn sway_windows() -> Option<Vec<WindowInfo>> {
+    std::env::var_os("SWAYSOCK")?;
+
+    fn walk(node: &Value, out: &mut Vec<WindowInfo>) {
+        let is_window = node["pid"].is_number() && node["visible"].as_bool().unwrap_or(false);
+        if is_window {
+            let rect = &node["rect"];
+            out.push(WindowInfo {
+                app_id: node["app_id"]
+                    .as_str()
+                    .or(node["window_properties"]["class"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                title: node["name"].as_str().unwrap_or_default().to_string(),
+                x: as_i32(&rect["x"]),
+                y: as_i32(&rect["y"]),
+                width: as_i32(&rect["width"]),
+                height: as_i32(&rect["height"]),
+            });
+        }
+        // 浮动窗口在平铺窗口之上，先加入
+        for key in ["floating_nodes", "nodes"] {
+            if let Some(children) = node[key].as_array() {
+                for child in children {
+                    walk(child, out);
+                }
+            }
+        }
+    }
+
+    let tree = run_json("swaymsg", &["-t", "get_tree", "-r"])?;
+    let mut windows = Vec::new();
+    walk(&tree, &mut windows);
+    Some(windows)
+}
+
+/// 查找全局坐标处最上层的窗口，当前合成器不支持时返回 None
+pub fn find_window_at(gx: i32, gy: i32) -> Option<WindowInfo> {
+    let windows = hyprland_windows().or_else(sway_windows)?;
+    let window = windows.into_iter().find(|w| w.contains(gx, gy));
+    debug!("window at ({}, {}): {:?}", gx, gy, window);
+    window
+}